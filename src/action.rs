@@ -1,17 +1,36 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use ratatui::text::Text;
-use tokio::sync::mpsc;
 
-use crate::{list_box::ListItemProvider, project::Project, utils};
+use crate::{
+    configure::{self, Configure},
+    ctest,
+    job::Output,
+    list_box::ListItemProvider,
+    project::{Project, TargetKind},
+    pty::{self, TerminalIo},
+    utils,
+};
 use eyre::Result;
 
+/// Where the program of a run or debug session reads and writes.
+pub enum Console {
+    /// A pseudo-terminal shown in the job's tab.
+    Terminal(TerminalIo),
+    /// Output piped into the job, without input.
+    Piped,
+    /// The standard streams of this process, so that a headless run can be
+    /// used in a pipeline.
+    Inherited,
+}
+
 #[derive(Default, PartialEq, Eq, Clone)]
 pub enum Action {
     #[default]
     Run,
     Build,
     Debug,
+    Test(ctest::Filter),
 }
 
 impl ListItemProvider for Action {
@@ -26,82 +45,178 @@ impl Action {
             Action::Run => "Run",
             Action::Build => "Build",
             Action::Debug => "Debug",
+            Action::Test(_) => "Test",
+        }
+    }
+
+    /// The actions that make sense for a target of the given kind.
+    pub fn available_for(kind: TargetKind) -> Vec<Action> {
+        match kind {
+            TargetKind::Executable => vec![
+                Action::Run,
+                Action::Build,
+                Action::Debug,
+                Action::Test(ctest::Filter::All),
+            ],
+            TargetKind::InterfaceLibrary => vec![Action::Test(ctest::Filter::All)],
+            _ => vec![Action::Build, Action::Test(ctest::Filter::All)],
         }
     }
 
     pub async fn run(
         &self,
-        out: &mpsc::Sender<Result<Text<'static>>>,
-        in_rx: mpsc::Receiver<Result<String>>,
+        out: &Output,
+        console: Console,
         project: &Project,
         dir: &Path,
+        build_dir: &Path,
+        configure: &Configure,
     ) -> Result<()> {
-        let path = dir.parent().unwrap().to_str().unwrap();
+        let path = dir
+            .parent()
+            .and_then(|p| p.to_str())
+            .ok_or_else(|| eyre::eyre!("invalid project path: {}", dir.display()))?;
+        // Only a new build tree is configured here. When CMake files changed,
+        // `cmake --build` regenerates the build system with the settings of
+        // the tree, rather than switching it to the selected build type.
+        if !build_dir.join("CMakeCache.txt").is_file() {
+            out.send(Ok(Text::raw(format!(
+                "=== Configuring ({})\n",
+                configure::Reason::NotConfigured.to_str()
+            ))))
+            .await?;
+            if configure
+                .run(out, Path::new(path), build_dir)
+                .await
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
         match self {
-            Action::Run => self.build_and_run(out, Some(in_rx), path, project).await,
-            Action::Build => self.build(out, path, project).await,
-            Action::Debug => self.build_and_debug(out, Some(in_rx), path, project).await,
+            Action::Run => {
+                self.build_and_run(out, console, path, build_dir, configure, project)
+                    .await
+            }
+            Action::Build => self.build(out, path, build_dir, configure, project).await,
+            Action::Debug => {
+                self.build_and_debug(out, console, path, build_dir, configure, project)
+                    .await
+            }
+            Action::Test(filter) => {
+                self.build_and_test(out, path, build_dir, configure, filter)
+                    .await
+            }
+        }
+    }
+
+    async fn build_and_test(
+        &self,
+        out: &Output,
+        path: &str,
+        build_dir: &Path,
+        configure: &Configure,
+        filter: &ctest::Filter,
+    ) -> Result<()> {
+        let args = configure.build_args(build_dir, None);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let env = configure.env(&BTreeMap::new());
+        let cwd = Path::new(path);
+        if utils::spawn_with_env(out, &configure.commands.cmake, &args, cwd, &env, "Build")
+            .await
+            .is_err()
+        {
+            return Ok(());
         }
+        let mut args = ctest::args(configure, build_dir, filter);
+        args.push("--output-on-failure".to_string());
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        utils::spawn_with_env(out, &configure.commands.ctest, &args, cwd, &env, "Test").await
     }
 
     async fn build_and_debug(
         &self,
-        out: &mpsc::Sender<Result<Text<'static>>>,
-        mut in_rx: Option<mpsc::Receiver<Result<String>>>,
+        out: &Output,
+        console: Console,
         path: &str,
+        build_dir: &Path,
+        configure: &Configure,
         project: &Project,
     ) -> Result<()> {
-        let result = self.build(out, path, project).await;
-        match result {
-            Err(..) => return Ok(()),
-            _ => {}
+        if self
+            .build(out, path, build_dir, configure, project)
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+        let Console::Terminal(terminal) = console else {
+            return Err(eyre::eyre!("Debugging needs the terminal interface"));
         };
-        utils::spawn_command(
-            out,
-            in_rx.take(),
-            "lldb",
-            &[&format!("build/{0}", project.file_name)],
-            path,
-            "Run",
-        )
-        .await
+        let debugger = configure
+            .debugger
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("No debugger found, install gdb or lldb"))?;
+        let executable = project.resolve_artifact(build_dir)?;
+        let (program, args) = debugger.command(&executable, &project.run.args);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let cwd = project.run.working_dir(Path::new(path));
+        let env = configure.env(&project.run.env);
+        pty::spawn(out, terminal, &program, &args, &cwd, &env, "Debug").await
     }
 
+    /// Runs the target on `console`.
     async fn build_and_run(
         &self,
-        out: &mpsc::Sender<Result<Text<'static>>>,
-        mut in_rx: Option<mpsc::Receiver<Result<String>>>,
+        out: &Output,
+        console: Console,
         path: &str,
+        build_dir: &Path,
+        configure: &Configure,
         project: &Project,
     ) -> Result<()> {
-        let result = self.build(out, path, project).await;
-        match result {
-            Err(..) => return Ok(()),
-            _ => {}
-        };
-        utils::spawn_command(
-            out,
-            in_rx.take(),
-            &format!("build/{0}", project.file_name),
-            &[],
-            path,
-            "Run",
-        )
-        .await
+        if self
+            .build(out, path, build_dir, configure, project)
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+        let executable = project.resolve_artifact(build_dir)?;
+        let executable = executable.to_string_lossy();
+        let args: Vec<&str> = project.run.args.iter().map(|a| a.as_str()).collect();
+        let cwd = project.run.working_dir(Path::new(path));
+        let env = configure.env(&project.run.env);
+        match console {
+            Console::Terminal(terminal) => {
+                pty::spawn(out, terminal, &executable, &args, &cwd, &env, "Run").await
+            }
+            Console::Piped => {
+                utils::spawn_with_env(out, &executable, &args, &cwd, &env, "Run").await
+            }
+            Console::Inherited => {
+                utils::spawn_inherited(out, &executable, &args, &cwd, &env, "Run").await
+            }
+        }
     }
 
     async fn build(
         &self,
-        out: &mpsc::Sender<Result<Text<'static>>>,
+        out: &Output,
         path: &str,
+        build_dir: &Path,
+        configure: &Configure,
         project: &Project,
     ) -> Result<()> {
-        utils::spawn_command(
+        let args = configure.build_args(build_dir, Some(&project.target));
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let env = configure.env(&BTreeMap::new());
+        utils::spawn_with_env(
             out,
-            None,
-            "cmake",
-            &["--build", "build", "-t", &project.target],
-            path,
+            &configure.commands.cmake,
+            &args,
+            Path::new(path),
+            &env,
             "Build",
         )
         .await
@@ -0,0 +1,344 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+/// Escape sequences longer than this are dropped rather than buffered.
+const MAX_SEQUENCE: usize = 256;
+const TAB_WIDTH: usize = 8;
+
+/// Turns a stream of bytes with ANSI escapes into styled lines. The style and
+/// incomplete UTF-8 characters or escape sequences carry over from one read
+/// to the next.
+#[derive(Default, Debug)]
+pub struct Decoder {
+    /// The bytes of an incomplete character or escape sequence.
+    pending: Vec<u8>,
+    style: Style,
+    line: Vec<Span<'static>>,
+    /// A `\r` was seen, the next text overwrites the line, like the status
+    /// line of ninja or a progress bar.
+    carriage_return: bool,
+}
+
+impl Decoder {
+    /// Decodes `bytes`, returning the lines they complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Line<'static>> {
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(bytes);
+        let mut lines = vec![];
+        let mut i = 0;
+        while i < data.len() {
+            match data[i] {
+                b'\n' => {
+                    self.carriage_return = false;
+                    lines.push(Line::from(std::mem::take(&mut self.line)));
+                    i += 1;
+                }
+                b'\r' => {
+                    self.carriage_return = true;
+                    i += 1;
+                }
+                b'\t' => {
+                    let width: usize = self.line.iter().map(|s| s.width()).sum();
+                    self.push_str(&" ".repeat(TAB_WIDTH - width % TAB_WIDTH));
+                    i += 1;
+                }
+                ESC => match self.escape(&data[i..]) {
+                    Some(len) => i += len,
+                    None if data.len() - i > MAX_SEQUENCE => i += 1,
+                    None => {
+                        self.pending = data[i..].to_vec();
+                        break;
+                    }
+                },
+                b if b < 0x20 || b == 0x7f => i += 1,
+                _ => {
+                    let end = data[i..]
+                        .iter()
+                        .position(|&b| b < 0x20 || b == 0x7f)
+                        .map_or(data.len(), |p| i + p);
+                    self.text(&data[i..end], end == data.len());
+                    i = end;
+                }
+            }
+        }
+        lines
+    }
+
+    /// The line being written, if any.
+    pub fn partial(&self) -> Option<Line<'static>> {
+        (!self.line.is_empty()).then(|| Line::from(self.line.clone()))
+    }
+
+    /// Ends the stream, returning the unterminated last line.
+    pub fn finish(&mut self) -> Option<Line<'static>> {
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            if pending[0] != ESC {
+                self.push_str(&String::from_utf8_lossy(&pending));
+            }
+        }
+        let line = self.partial();
+        self.line.clear();
+        line
+    }
+
+    /// Decodes text, keeping an incomplete character at the end of the data
+    /// for the next read.
+    fn text(&mut self, mut bytes: &[u8], at_end: bool) {
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(s) => {
+                    self.push_str(s);
+                    return;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    self.push_str(std::str::from_utf8(valid).expect("valid UTF-8"));
+                    match e.error_len() {
+                        Some(len) => {
+                            self.push_str("\u{fffd}");
+                            bytes = &rest[len..];
+                        }
+                        None if at_end => {
+                            self.pending = rest.to_vec();
+                            return;
+                        }
+                        None => {
+                            self.push_str("\u{fffd}");
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if self.carriage_return {
+            self.carriage_return = false;
+            self.line.clear();
+        }
+        match self.line.last_mut() {
+            Some(span) if span.style == self.style => span.content.to_mut().push_str(s),
+            _ => self.line.push(Span::styled(s.to_string(), self.style)),
+        }
+    }
+
+    /// Handles the escape sequence at the start of `data`, returning its
+    /// length, or `None` when it is incomplete.
+    fn escape(&mut self, data: &[u8]) -> Option<usize> {
+        match data.get(1)? {
+            b'[' => {
+                let end = data[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+                if data[end] == b'm' {
+                    self.sgr(&String::from_utf8_lossy(&data[2..end]));
+                }
+                Some(end + 1)
+            }
+            // Operating system commands such as the window title, ended by
+            // BEL or ST.
+            b']' => {
+                let end = data
+                    .windows(2)
+                    .position(|w| w[0] == BEL || w == [ESC, b'\\']);
+                match end {
+                    Some(end) if data[end] == BEL => Some(end + 1),
+                    Some(end) => Some(end + 2),
+                    None if data.last() == Some(&BEL) => Some(data.len()),
+                    None => None,
+                }
+            }
+            // Character set selection, e.g. `ESC ( B`.
+            b'(' | b')' => data.get(2).map(|_| 3),
+            _ => Some(2),
+        }
+    }
+
+    /// Applies a Select Graphic Rendition sequence, e.g. `1;31`.
+    fn sgr(&mut self, params: &str) {
+        let mut params = params
+            .split([';', ':'])
+            .map(|p| p.parse::<u8>().unwrap_or(0));
+        while let Some(param) = params.next() {
+            let style = &mut self.style;
+            *style = match param {
+                0 => Style::default(),
+                1 => style.add_modifier(Modifier::BOLD),
+                2 => style.add_modifier(Modifier::DIM),
+                3 => style.add_modifier(Modifier::ITALIC),
+                4 => style.add_modifier(Modifier::UNDERLINED),
+                5 => style.add_modifier(Modifier::SLOW_BLINK),
+                6 => style.add_modifier(Modifier::RAPID_BLINK),
+                7 => style.add_modifier(Modifier::REVERSED),
+                8 => style.add_modifier(Modifier::HIDDEN),
+                9 => style.add_modifier(Modifier::CROSSED_OUT),
+                21 | 22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+                23 => style.remove_modifier(Modifier::ITALIC),
+                24 => style.remove_modifier(Modifier::UNDERLINED),
+                25 => style.remove_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+                27 => style.remove_modifier(Modifier::REVERSED),
+                28 => style.remove_modifier(Modifier::HIDDEN),
+                29 => style.remove_modifier(Modifier::CROSSED_OUT),
+                30..=37 => style.fg(NORMAL[(param - 30) as usize]),
+                90..=97 => style.fg(BRIGHT[(param - 90) as usize]),
+                40..=47 => style.bg(NORMAL[(param - 40) as usize]),
+                100..=107 => style.bg(BRIGHT[(param - 100) as usize]),
+                38 => match extended_color(&mut params) {
+                    Some(color) => style.fg(color),
+                    None => *style,
+                },
+                48 => match extended_color(&mut params) {
+                    Some(color) => style.bg(color),
+                    None => *style,
+                },
+                // Inherit the style of the stream, e.g. stderr.
+                39 => Style { fg: None, ..*style },
+                49 => Style { bg: None, ..*style },
+                _ => *style,
+            };
+        }
+    }
+}
+
+const NORMAL: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+];
+
+const BRIGHT: [Color; 8] = [
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Reads the rest of a 256 color (`5;n`) or true color (`2;r;g;b`) parameter.
+fn extended_color(params: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(Color::Indexed(params.next()?)),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+const MODIFIERS: [(Modifier, u8); 9] = [
+    (Modifier::BOLD, 1),
+    (Modifier::DIM, 2),
+    (Modifier::ITALIC, 3),
+    (Modifier::UNDERLINED, 4),
+    (Modifier::SLOW_BLINK, 5),
+    (Modifier::RAPID_BLINK, 6),
+    (Modifier::REVERSED, 7),
+    (Modifier::HIDDEN, 8),
+    (Modifier::CROSSED_OUT, 9),
+];
+
+/// Writes `line` back as text with SGR escapes, the reverse of [`Decoder`].
+pub fn encode(line: &Line) -> String {
+    let mut text = String::new();
+    let mut current = Style::default();
+    for span in &line.spans {
+        let style = line.style.patch(span.style);
+        if style != current {
+            text.push_str(&sgr(style));
+            current = style;
+        }
+        text.push_str(&span.content);
+    }
+    if current != Style::default() {
+        text.push_str("\x1b[0m");
+    }
+    text
+}
+
+/// The sequence resetting the style to `style`.
+fn sgr(style: Style) -> String {
+    let mut params = vec!["0".to_string()];
+    params.extend(
+        MODIFIERS
+            .iter()
+            .filter(|(modifier, _)| style.add_modifier.contains(*modifier))
+            .map(|(_, code)| code.to_string()),
+    );
+    params.extend(style.fg.and_then(|c| color_param(c, 30)));
+    params.extend(style.bg.and_then(|c| color_param(c, 40)));
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// The parameter selecting `color`, `base` being 30 for the foreground and
+/// 40 for the background.
+fn color_param(color: Color, base: u8) -> Option<String> {
+    if let Some(i) = NORMAL.iter().position(|&c| c == color) {
+        return Some((base + i as u8).to_string());
+    }
+    if let Some(i) = BRIGHT.iter().position(|&c| c == color) {
+        return Some((base + 60 + i as u8).to_string());
+    }
+    match color {
+        Color::Indexed(n) => Some(format!("{};5;{n}", base + 8)),
+        Color::Rgb(r, g, b) => Some(format!("{};2;{r};{g};{b}", base + 8)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `chunks` one after the other, returning the text of the lines.
+    fn decode(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = Decoder::default();
+        let mut lines: Vec<_> = chunks.iter().flat_map(|c| decoder.feed(c)).collect();
+        lines.extend(decoder.finish());
+        lines.iter().map(Line::to_string).collect()
+    }
+
+    #[test]
+    fn joins_characters_split_across_reads() {
+        let bytes = "é✓\n".as_bytes();
+        assert_eq!(decode(&[&bytes[..1], &bytes[1..3], &bytes[3..]]), ["é✓"]);
+        assert_eq!(decode(&[b"a\xff\n"]), ["a\u{fffd}"]);
+    }
+
+    #[test]
+    fn joins_escapes_split_across_reads() {
+        let mut decoder = Decoder::default();
+        assert!(decoder.feed(b"a\x1b").is_empty());
+        assert!(decoder.feed(b"[1;3").is_empty());
+        let lines = decoder.feed(b"1mb\x1b]0;title\x07\n");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].to_string(), "ab");
+        assert_eq!(lines[0].spans[0].style, Style::default());
+        assert_eq!(
+            lines[0].spans[1].style,
+            Style::new().fg(Color::Red).add_modifier(Modifier::BOLD)
+        );
+    }
+
+    #[test]
+    fn overwrites_the_line_after_a_carriage_return() {
+        let mut decoder = Decoder::default();
+        assert!(decoder.feed(b"[1/2] a\r").is_empty());
+        assert_eq!(decoder.partial().unwrap().to_string(), "[1/2] a");
+        assert!(decoder.feed(b"[2/2] b").is_empty());
+        assert_eq!(decoder.partial().unwrap().to_string(), "[2/2] b");
+        assert_eq!(decode(&[b"10%\r", b"100%\r\n", b"done"]), ["100%", "done"]);
+    }
+}
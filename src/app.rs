@@ -1,57 +1,147 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 pub mod io;
 use super::*;
 use crate::{
-    action::Action,
+    action::{Action, Console},
+    build_dir::{self, BuildDir},
+    config::{Config, keys::KeyAction},
+    configure::{self, Configure},
+    ctest,
+    debugger::Debugger,
+    diagnostic::Diagnostic,
+    editor::{self, Location},
+    job::{self, Control, Job, Output},
+    line_buffer::{LineBuffer, Scroll},
     list_box::{ListBox, state::ListBoxState},
-    project::Project,
+    log::{self, LogFormat, Tee},
+    presets::{self, ConfigurePreset},
+    project::{self, Project, RunConfig},
+    pty,
+    run_editor::{EditorEvent, RunEditor},
+    search::{self, Search},
+    state::State,
     stateful_list::StatefulList,
+    text_input::{InputEvent, TextInput},
 };
-use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, EventStream, KeyEvent};
 use eyre::Result;
 use io::AppIo;
 use ratatui::{
     DefaultTerminal,
     text::{Span, Text},
 };
-use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 
 #[derive(Default)]
 pub struct App {
     projects: ListBoxState<Project>,
     actions: ListBoxState<Action>,
+    presets: ListBoxState<ConfigurePreset>,
     path: PathBuf,
-    output: Vec<Text<'static>>,
+    build_dir: PathBuf,
+    build_dirs: ListBoxState<BuildDir>,
+    configure: Configure,
+    configure_reason: Option<configure::Reason>,
+    /// Messages that belong to no job, shown in the first tab.
+    output: LineBuffer,
     io: AppIo,
-    in_tx: Option<mpsc::Sender<Result<String>>>,
-    offset: u16,
+    jobs: ListBoxState<Job>,
+    next_job_id: usize,
+    /// The selected output tab: 0 for the messages, then one per job.
+    tab: usize,
+    picking_job: bool,
+    /// The last launched action, for restarting it.
+    last_launch: Option<(Action, Project)>,
+    /// Actions launched while the same one was still running, with the id
+    /// of the job they wait for, started when it ends.
+    queued: Vec<(Action, Project, usize)>,
+    /// The size of the output pane, for the terminal of the next program.
+    terminal_size: (u16, u16),
     exit: bool,
     input: bool,
+    picking_build_dir: bool,
+    tests: ListBoxState<ctest::Test>,
+    test_prompt: Option<(TestPrompt, TextInput)>,
+    picking_test: bool,
+    /// Whether `ctest` is listing the tests for the picker.
+    listing_tests: bool,
+    run_editor: Option<RunEditor>,
+    state: State,
+    /// The state as last written, to only save when something changed.
+    saved_state: State,
+    config: Config,
+    /// A file to open in the editor once the current event is handled.
+    editing: Option<Location>,
+    search: Option<Search>,
+    /// The fuzzy query of the projects being typed.
+    finding_project: Option<TextInput>,
+}
+
+/// The title of the job of an explicit configure step.
+const CONFIGURE_JOB: &str = "Configure";
+
+/// How many lines to show above a problem or match jumped to.
+const JUMP_CONTEXT: usize = 2;
+
+enum TestPrompt {
+    Regex,
+    Labels,
 }
 
 impl App {
     const FRAMES_PER_SECOND: f32 = 60.0;
 
-    pub fn new(path: PathBuf) -> Self {
+    /// Creates the app for the `CMakeLists.txt` at `path`, restoring the state
+    /// of the last session. A build directory given on the command line wins
+    /// over the remembered one.
+    pub fn new(
+        path: PathBuf,
+        build_dir: Option<PathBuf>,
+        mut configure: Configure,
+        config: Config,
+    ) -> Self {
+        let source_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut output = LineBuffer::new(config.output.scrollback);
+        let state = State::load(&source_dir).unwrap_or_else(|e| {
+            output.push_text(Text::raw(format!("{e:#}")).light_red());
+            State::default()
+        });
+        let build_dir = build_dir::initial(&source_dir, build_dir.or(state.build_dir.clone()));
+        configure.commands = config.commands.clone();
+        configure.debugger = Debugger::detect(&config.commands);
         Self {
+            config,
+            build_dir,
+            configure,
+            output,
+            saved_state: state.clone(),
+            state,
             actions: ListBoxState {
-                list: StatefulList {
-                    items: vec![Action::Run, Action::Build, Action::Debug],
-                    ..Default::default()
-                },
+                list: StatefulList::default(),
                 active: false,
+                filter: None,
+            },
+            presets: ListBoxState {
+                list: StatefulList::default(),
+                active: false,
+                filter: None,
             },
             path,
             input: false,
-            in_tx: None,
+            terminal_size: (24, 80),
             ..Default::default()
         }
     }
 
     pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
-        self.refresh_list()?;
+        if let Err(e) = self.refresh_list() {
+            self.show_error(format!("{e:#}"));
+        }
 
         let period = Duration::from_secs_f32(1.0 / Self::FRAMES_PER_SECOND);
         let mut interval = tokio::time::interval(period);
@@ -59,19 +149,61 @@ impl App {
 
         while !self.exit {
             tokio::select! {
-                _ = interval.tick() => {terminal.draw(|frame| self.draw(frame))?;},
-                Some(Ok(event)) = events.next() => self.handle_events(&event)?,
-                Some(line) = self.io.out_rx.recv() => {
-                    match line {
-                        Ok(line) => self.output.push(line),
-                        Err(e) => self.output.push(Text::raw(e.to_string()).light_red()),
+                _ = interval.tick() => {
+                    self.update_jobs();
+                    terminal.draw(|frame| self.draw(frame))?;
+                },
+                Some(Ok(event)) = events.next() => {
+                    self.handle_events(&event)?;
+                    self.save_state();
+                }
+                Some((id, line)) = self.io.out_rx.recv() => {
+                    if let Some(job) = self.jobs.list.items.iter_mut().find(|j| j.id == id) {
+                        job.push(line);
                     }
                 }
+                Some(tests) = self.io.tests_rx.recv() => self.show_test_picker(tests),
             };
+            if let Some(location) = self.editing.take() {
+                // The event stream reads from the terminal in the background,
+                // it would steal the editor's input.
+                drop(events);
+                self.open_editor(terminal, &location)?;
+                events = EventStream::new();
+            }
+        }
+        self.save_state();
+        Ok(())
+    }
+
+    /// Hands the terminal over to the editor until it exits. Jobs are paused
+    /// meanwhile.
+    fn open_editor(&mut self, terminal: &mut DefaultTerminal, location: &Location) -> Result<()> {
+        ratatui::restore();
+        let result = editor::open(&self.config.commands, location);
+        *terminal = ratatui::init();
+        terminal.clear()?;
+        if let Err(e) = result {
+            self.show_error(format!("{e:#}"));
         }
         Ok(())
     }
 
+    /// Records the current selections in the state, writing it out when it
+    /// changed.
+    fn save_state(&mut self) {
+        self.state.project = self.projects.get_selected().map(|p| p.target.clone());
+        self.state.action = self.actions.get_selected().map(|a| a.to_str().to_string());
+        self.state.build_dir = Some(self.build_dir.clone());
+        if self.state == self.saved_state {
+            return;
+        }
+        if let Err(e) = self.state.save(&self.source_dir()) {
+            self.show_error(format!("{e:#}"));
+        }
+        self.saved_state = self.state.clone();
+    }
+
     fn draw(&mut self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
     }
@@ -84,114 +216,887 @@ impl App {
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<()> {
+        if self.picking_build_dir {
+            self.handle_build_dir_key_event(key_event);
+            return Ok(());
+        }
+        if self.picking_test {
+            self.handle_test_key_event(key_event);
+            return Ok(());
+        }
+        if self.picking_job {
+            self.handle_job_key_event(key_event);
+            return Ok(());
+        }
+        if self.current_job().is_some_and(|j| j.problems.active) {
+            self.handle_problem_key_event(key_event);
+            return Ok(());
+        }
+        if let Some(editor) = &mut self.run_editor {
+            match editor.handle_key_event(key_event, &self.config.keys) {
+                EditorEvent::Save(config) => {
+                    self.run_editor = None;
+                    self.save_run_config(config);
+                }
+                EditorEvent::Cancel => self.run_editor = None,
+                EditorEvent::Editing => {}
+            }
+            return Ok(());
+        }
+        if self.search.as_ref().is_some_and(|s| s.editing) {
+            self.handle_search_key_event(key_event);
+            return Ok(());
+        }
+        if self.finding_project.is_some() {
+            self.handle_find_project_key_event(key_event);
+            return Ok(());
+        }
         if self.input {
-            if key_event.code == KeyCode::Char('i') && key_event.modifiers == KeyModifiers::ALT {
+            // Every key but the one leaving input mode goes to the program,
+            // Ctrl+C included.
+            if self.config.keys.is(KeyAction::Input, &key_event) {
                 self.input = false;
-                self.in_tx = None;
-            } else if let Some(in_tx) = &self.in_tx {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    match key_event.code {
-                        KeyCode::Char('j') => {
-                            self.offset += 1;
-                        }
-                        KeyCode::Char('k') => {
-                            self.offset = self.offset.saturating_sub(1);
-                        }
-                        _ => {}
-                    }
-                    return Ok(());
-                }
-
-                let mut buf = [0; 4];
-                let str_buf = match key_event.code {
-                    KeyCode::Char(c) => c.encode_utf8(&mut buf).to_string(),
-                    KeyCode::Enter => "\n".to_string(),
-                    _ => "".to_string(),
-                };
-
-                if !str_buf.is_empty() {
-                    if let Some(l) = self.output.last_mut() {
-                        l.push_span(format!("{str_buf}"));
-                    }
-                    in_tx.try_send(Ok(str_buf)).ok();
-                }
+            } else if let Some(terminal) = self.current_job().and_then(|j| j.terminal.as_ref()) {
+                terminal.send_key(key_event);
             }
             return Ok(());
         }
 
-        match key_event.code {
-            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+        let Some(action) = self.config.keys.action(&key_event) else {
+            return Ok(());
+        };
+        match action {
+            KeyAction::Quit => {
                 self.exit = true;
             }
-            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::SHIFT => {
-                self.refresh_list()?
+            KeyAction::Refresh => {
+                if let Err(e) = self.refresh_list() {
+                    self.show_error(format!("{e:#}"));
+                }
             }
-            KeyCode::Char('b') if key_event.modifiers == KeyModifiers::CONTROL => {
+            KeyAction::Configure => {
                 self.cmake_configure()?;
             }
-            KeyCode::Char('a') => {
-                self.projects.active = !self.projects.active;
-                self.actions.active = !self.actions.active
+            KeyAction::BuildDir => self.open_build_dir_picker(),
+            KeyAction::EditRun => {
+                if let Some(project) = self.projects.get_selected() {
+                    self.run_editor =
+                        Some(RunEditor::new(&project.target, &project.run, &self.config));
+                }
+            }
+            KeyAction::Generator => self.configure.generator = self.configure.generator.next(),
+            KeyAction::BuildType => {
+                self.configure.next_build_type();
+                // Multi-config trees have other artifacts per build type.
+                if let Err(e) = self.refresh_list() {
+                    self.show_error(format!("{e:#}"));
+                }
+            }
+            KeyAction::Focus => self.focus_next(),
+            KeyAction::Up if self.presets.active => self.presets.prev(),
+            KeyAction::Down if self.presets.active => self.presets.next(),
+            KeyAction::Select if self.presets.active => self.apply_preset(),
+            KeyAction::Up if self.projects.active => {
+                self.projects.prev();
+                self.sync_actions();
+            }
+            KeyAction::Up if self.actions.active => self.actions.prev(),
+            KeyAction::Down if self.projects.active => {
+                self.projects.next();
+                self.sync_actions();
             }
-            KeyCode::Up if self.projects.active => self.projects.prev(),
-            KeyCode::Up if self.actions.active => self.actions.prev(),
-            KeyCode::Down if self.projects.active => self.projects.next(),
-            KeyCode::Down if self.actions.active => self.actions.next(),
-            KeyCode::Enter => {
+            KeyAction::Down if self.actions.active => self.actions.next(),
+            KeyAction::Select => {
                 if let (Some(action), Some(project)) = (
                     self.actions.get_selected().cloned(),
                     self.projects.get_selected().cloned(),
                 ) {
-                    self.output.clear();
+                    match action {
+                        Action::Test(_) => self.open_test_picker(),
+                        Action::Debug if self.configure.debugger.is_none() => self.show_error(
+                            "No debugger found, install gdb or lldb or set commands.debugger",
+                        ),
+                        action => self.launch(action, project),
+                    }
+                }
+            }
+            KeyAction::ScrollDown => self.scroll(Scroll::down, 1),
+            KeyAction::ScrollUp => self.scroll(Scroll::up, 1),
+            KeyAction::PageDown => self.scroll(Scroll::down, self.page()),
+            KeyAction::PageUp => self.scroll(Scroll::up, self.page()),
+            KeyAction::ScrollTop => self.scroll(|scroll, _, _, _| scroll.to(0), 0),
+            KeyAction::ScrollBottom => self.scroll(|scroll, _, _, _| scroll.follow = true, 0),
+            KeyAction::Search => self.open_search(false),
+            KeyAction::Filter => self.open_search(true),
+            KeyAction::NextMatch => self.jump_to_match(true),
+            KeyAction::PrevMatch => self.jump_to_match(false),
+            KeyAction::Cancel if self.search.is_some() => self.search = None,
+            KeyAction::Cancel if self.projects.filter.is_some() => {
+                self.projects.set_filter(None);
+                self.sync_actions();
+            }
+            KeyAction::FindProject => self.find_project(),
+            KeyAction::SaveLog => self.save_output(LogFormat::Plain),
+            KeyAction::SaveAnsiLog => self.save_output(LogFormat::Ansi),
+            KeyAction::Input => {
+                self.input = true;
+            }
+            KeyAction::Stop => self.stop_job(),
+            KeyAction::Restart => self.restart(),
+            KeyAction::NextTab => self.tab = (self.tab + 1) % (self.jobs.list.items.len() + 1),
+            KeyAction::PrevTab => {
+                self.tab = self
+                    .tab
+                    .checked_sub(1)
+                    .unwrap_or(self.jobs.list.items.len())
+            }
+            KeyAction::CloseTab => self.close_tab(),
+            KeyAction::Jobs if !self.jobs.list.items.is_empty() => {
+                self.jobs
+                    .list
+                    .state
+                    .select(Some(self.tab.saturating_sub(1)));
+                self.picking_job = true;
+            }
+            KeyAction::Problems => {
+                if let Some(job) = self
+                    .current_job_mut()
+                    .filter(|j| !j.problems.list.items.is_empty())
+                {
+                    job.problems.active = true;
+                }
+            }
+            KeyAction::Edit => {
+                let problem = self
+                    .current_job()
+                    .and_then(|j| j.problems.get_selected())
+                    .filter(|_| !self.projects.active);
+                self.editing = match problem {
+                    Some(diagnostic) => Some(self.problem_location(diagnostic)),
+                    None => self.projects.get_selected().map(|project| Location {
+                        file: self.source_dir().join(&project.source),
+                        line: project.line,
+                        column: None,
+                    }),
+                };
+            }
+            KeyAction::NextProblem => self.jump_to_problem(ListBoxState::next),
+            KeyAction::PrevProblem => self.jump_to_problem(ListBoxState::prev),
+            _ => {}
+        }
+        Ok(())
+    }
 
-                    let out_tx = self.io.out_tx.clone();
-                    let (command_in_tx, command_in_rx) = mpsc::channel(50);
-                    self.in_tx = Some(command_in_tx);
+    /// Remembers how to run the selected project.
+    fn save_run_config(&mut self, config: RunConfig) {
+        let Some(index) = self.projects.list.state.selected() else {
+            return;
+        };
+        let Some(project) = self.projects.list.items.get_mut(index) else {
+            return;
+        };
+        if config.is_empty() {
+            self.state.targets.remove(&project.target);
+        } else {
+            self.state
+                .targets
+                .insert(project.target.clone(), config.clone());
+        }
+        project.run = config;
+    }
 
-                    let path = self.path.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = action.run(&out_tx, command_in_rx, &project, &path).await {
-                            out_tx.send(Err(e)).await.ok();
-                        }
-                    });
+    fn current_job(&self) -> Option<&Job> {
+        self.tab
+            .checked_sub(1)
+            .and_then(|i| self.jobs.list.items.get(i))
+    }
+
+    fn current_job_mut(&mut self) -> Option<&mut Job> {
+        self.tab
+            .checked_sub(1)
+            .and_then(|i| self.jobs.list.items.get_mut(i))
+    }
+
+    /// The output of the selected tab.
+    fn output_mut(&mut self) -> &mut LineBuffer {
+        match self.tab.checked_sub(1) {
+            Some(i) if i < self.jobs.list.items.len() => &mut self.jobs.list.items[i].output,
+            _ => &mut self.output,
+        }
+    }
+
+    fn current_output(&self) -> &LineBuffer {
+        match self.current_job() {
+            Some(job) => &job.output,
+            None => &self.output,
+        }
+    }
+
+    /// Scrolls the output of the selected tab, or the matching lines when
+    /// filtering.
+    fn scroll(&mut self, step: fn(&mut Scroll, usize, usize, usize), lines: usize) {
+        let rows = usize::from(self.terminal_size.0);
+        let filtered = self
+            .search
+            .as_ref()
+            .filter(|s| s.filter)
+            .map(|s| s.matches(self.current_output()).len());
+        match (filtered, &mut self.search) {
+            (Some(height), Some(search)) => step(&mut search.scroll, lines, height, rows),
+            _ => {
+                let output = self.output_mut();
+                let height = output.height();
+                step(output.scroll_mut(), lines, height, rows);
+            }
+        }
+    }
+
+    /// How far PageUp and PageDown scroll, keeping a line of context.
+    fn page(&self) -> usize {
+        usize::from(self.terminal_size.0).saturating_sub(1).max(1)
+    }
+
+    /// Shows a message that belongs to no job.
+    fn show_error(&mut self, message: impl Into<String>) {
+        self.output.push_text(Text::raw(message.into()).light_red());
+        self.tab = 0;
+    }
+
+    /// The running job that keeps the job called `title` from starting: the
+    /// same action, or else a configure step.
+    fn blocking_job(&mut self, title: &str) -> Option<&mut Job> {
+        let jobs = &mut self.jobs.list.items;
+        let index = jobs
+            .iter()
+            .position(|j| j.title == title && j.is_running())
+            .or_else(|| {
+                jobs.iter()
+                    .position(|j| j.title == CONFIGURE_JOB && j.is_running())
+            })?;
+        jobs.get_mut(index)
+    }
+
+    /// Runs `task` as a new job in its own tab, replacing the tab of the
+    /// finished job with the same title.
+    fn spawn_job<F, Fut>(&mut self, title: String, launch: Option<(Action, Project)>, task: F)
+    where
+        F: FnOnce(Output) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        let control = Control::default();
+        let mut out = Output::new(id, self.io.out_tx.clone(), control.clone());
+        out.stderr = self.config.theme.stderr;
+        let future = task(out.clone());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = future.await {
+                out.control.set_failed();
+                out.send(Err(e)).await.ok();
+            }
+        });
+        let mut job = Job::new(
+            id,
+            title,
+            launch,
+            handle,
+            control,
+            self.config.output.scrollback,
+        );
+        if let Some(format) = self.config.output.tee {
+            let output = &self.config.output;
+            match Tee::open(&self.source_dir(), &job.title, format, output.logs) {
+                Ok(log) => job.log = Some(log),
+                Err(e) => job
+                    .output
+                    .push_text(Text::raw(format!("{e:#}")).light_red()),
+            }
+        }
+
+        let jobs = &mut self.jobs.list.items;
+        let index = match jobs.iter().position(|j| j.title == job.title) {
+            Some(index) => {
+                jobs[index] = job;
+                index
+            }
+            None => {
+                jobs.push(job);
+                jobs.len() - 1
+            }
+        };
+        self.tab = index + 1;
+    }
+
+    /// Launches `action` as a new job, or queues it while the same action
+    /// is still running.
+    fn launch(&mut self, action: Action, project: Project) {
+        let title = job_title(&action, &project);
+        if let Some(job) = self.blocking_job(&title) {
+            job.output.push_text(
+                Text::raw(format!("\n=== {title} queued until this job ends\n")).light_yellow(),
+            );
+            let id = job.id;
+            self.queued.push((action, project, id));
+            return;
+        }
+        self.last_launch = Some((action.clone(), project.clone()));
+
+        let (rows, cols) = self.terminal_size;
+        let (terminal, terminal_io) = pty::channel(rows, cols, self.config.output.scrollback);
+
+        let path = self.path.clone();
+        let build_dir = self.build_dir.clone();
+        let configure = self.configure.clone();
+        self.configure_reason = None;
+        let launch = Some((action.clone(), project.clone()));
+        self.spawn_job(title, launch, |out| async move {
+            action
+                .run(
+                    &out,
+                    Console::Terminal(terminal_io),
+                    &project,
+                    &path,
+                    &build_dir,
+                    &configure,
+                )
+                .await
+        });
+        if let Some(job) = self.current_job_mut() {
+            job.terminal = Some(terminal);
+        }
+    }
+
+    /// Records finished jobs and starts the queued actions that may run now.
+    fn update_jobs(&mut self) {
+        for job in &mut self.jobs.list.items {
+            job.update();
+        }
+        // Those still blocked by another job are queued behind that one.
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.queued)
+            .into_iter()
+            .partition(|(_, _, id)| {
+                !self
+                    .jobs
+                    .list
+                    .items
+                    .iter()
+                    .any(|j| j.id == *id && j.is_running())
+            });
+        self.queued = waiting;
+        for (action, project, _) in ready {
+            self.launch(action, project);
+        }
+    }
+
+    /// Stops the job of the selected tab, harder on every call.
+    fn stop_job(&mut self) {
+        let Some(job) = self.current_job_mut().filter(|j| j.is_running()) else {
+            return;
+        };
+        let signal = job.stop();
+        job.output
+            .push_text(Text::raw(format!("\n=== Sending {}\n", signal.to_str())).light_yellow());
+        let id = job.id;
+        self.cancel_queued(id);
+    }
+
+    /// Drops the launches queued behind the job `id`, which was stopped.
+    fn cancel_queued(&mut self, id: usize) {
+        let (cancelled, queued): (Vec<_>, Vec<_>) = std::mem::take(&mut self.queued)
+            .into_iter()
+            .partition(|(_, _, blocker)| *blocker == id);
+        self.queued = queued;
+        let Some(job) = self.jobs.list.items.iter_mut().find(|j| j.id == id) else {
+            return;
+        };
+        for (action, project, _) in cancelled {
+            let title = job_title(&action, &project);
+            job.output
+                .push_text(Text::raw(format!("=== {title} cancelled\n")).light_yellow());
+        }
+    }
+
+    /// Launches the action of the selected tab again, or else the last one,
+    /// with the current settings of its project. A running job is stopped
+    /// first.
+    fn restart(&mut self) {
+        let launch = self
+            .current_job()
+            .and_then(|j| j.launch.clone())
+            .or_else(|| self.last_launch.clone());
+        let Some((action, project)) = launch else {
+            return;
+        };
+        let project = self
+            .projects
+            .list
+            .items
+            .iter()
+            .find(|p| p.target == project.target)
+            .cloned()
+            .unwrap_or(project);
+        let title = job_title(&action, &project);
+        if let Some(job) = self
+            .jobs
+            .list
+            .items
+            .iter_mut()
+            .find(|j| j.title == title && j.is_running())
+        {
+            job.stop();
+        }
+        self.launch(action, project);
+    }
+
+    /// Closes the tab of a finished job.
+    fn close_tab(&mut self) {
+        let Some(index) = self.tab.checked_sub(1) else {
+            return;
+        };
+        if self
+            .jobs
+            .list
+            .items
+            .get(index)
+            .is_some_and(|j| !j.is_running())
+        {
+            self.jobs.list.items.remove(index);
+            self.tab = self.tab.min(self.jobs.list.items.len());
+        }
+    }
+
+    fn handle_job_key_event(&mut self, key_event: KeyEvent) {
+        match self.config.keys.action(&key_event) {
+            Some(KeyAction::Up) => self.jobs.prev(),
+            Some(KeyAction::Down) => self.jobs.next(),
+            Some(KeyAction::Cancel | KeyAction::Jobs) => self.picking_job = false,
+            Some(KeyAction::Select) => {
+                self.picking_job = false;
+                if let Some(index) = self.jobs.list.state.selected() {
+                    self.tab = index + 1;
                 }
             }
-            KeyCode::Char('j') => {
-                self.offset += 1;
+            Some(KeyAction::Stop) => {
+                if let Some(job) = self
+                    .jobs
+                    .list
+                    .state
+                    .selected()
+                    .and_then(|i| self.jobs.list.items.get_mut(i))
+                    .filter(|j| j.is_running())
+                {
+                    job.stop();
+                    let id = job.id;
+                    self.cancel_queued(id);
+                }
             }
-            KeyCode::Char('k') => {
-                self.offset = self.offset.saturating_sub(1);
+            _ => {}
+        }
+    }
+
+    fn handle_problem_key_event(&mut self, key_event: KeyEvent) {
+        match self.config.keys.action(&key_event) {
+            Some(KeyAction::Up) => self.jump_to_problem(ListBoxState::prev),
+            Some(KeyAction::Down) => self.jump_to_problem(ListBoxState::next),
+            Some(KeyAction::Select | KeyAction::Cancel | KeyAction::Problems) => {
+                if let Some(job) = self.current_job_mut() {
+                    job.problems.active = false;
+                }
             }
-            KeyCode::Char('i') if key_event.modifiers == KeyModifiers::ALT => {
-                self.input = true;
+            Some(KeyAction::Edit) => {
+                self.editing = self
+                    .current_job()
+                    .and_then(|j| j.problems.get_selected())
+                    .map(|diagnostic| self.problem_location(diagnostic));
             }
             _ => {}
         }
-        Ok(())
+    }
+
+    fn problem_location(&self, diagnostic: &Diagnostic) -> Location {
+        Location {
+            file: editor::resolve(&diagnostic.file, &self.build_dir, &self.source_dir()),
+            line: diagnostic.line as usize,
+            column: diagnostic.column.map(|c| c as usize),
+        }
+    }
+
+    /// Moves the problem selection of the current job with `step` and scrolls
+    /// the output to it.
+    fn jump_to_problem(&mut self, step: fn(&mut ListBoxState<Diagnostic>)) {
+        let Some(job) = self.current_job_mut() else {
+            return;
+        };
+        step(&mut job.problems);
+        if let Some(diagnostic) = job.problems.get_selected() {
+            let line = diagnostic.output_line.saturating_sub(JUMP_CONTEXT);
+            job.output.scroll_to(line);
+        }
+    }
+
+    /// Starts typing a search, or a filter, with the last pattern.
+    fn open_search(&mut self, filter: bool) {
+        let output = self.current_output();
+        let origin = output.first() + output.offset(usize::from(self.terminal_size.0));
+        let pattern = self.search.as_ref().map(|s| s.pattern().to_string());
+        self.search = Some(Search::new(
+            filter,
+            &pattern.unwrap_or_default(),
+            origin,
+            self.config.theme,
+        ));
+        self.search_from_origin();
+    }
+
+    /// Writes the output shown to a file in the build directory.
+    fn save_output(&mut self, format: LogFormat) {
+        let title = self.current_job().map_or("messages", |j| j.title.as_str());
+        match log::export(self.current_output().iter(), &self.build_dir, title, format) {
+            Ok(path) => {
+                let message = format!("\n=== Saved the output to {}\n", path.display());
+                self.output_mut()
+                    .push_text(Text::raw(message).light_green());
+            }
+            Err(e) => self.show_error(format!("{e:#}")),
+        }
+    }
+
+    /// Focuses the projects and starts typing a fuzzy query, resuming the
+    /// current one if any.
+    fn find_project(&mut self) {
+        (
+            self.projects.active,
+            self.actions.active,
+            self.presets.active,
+        ) = (true, false, false);
+        let query = self
+            .projects
+            .filter
+            .as_ref()
+            .map(|f| f.query.clone())
+            .unwrap_or_default();
+        self.finding_project = Some(TextInput::new("Find project", query).theme(self.config.theme));
+    }
+
+    fn handle_find_project_key_event(&mut self, key_event: KeyEvent) {
+        let Some(input) = &mut self.finding_project else {
+            return;
+        };
+        if self.config.keys.is(KeyAction::Up, &key_event) {
+            self.projects.prev();
+        } else if self.config.keys.is(KeyAction::Down, &key_event) {
+            self.projects.next();
+        } else {
+            match input.handle_key_event(key_event) {
+                InputEvent::Submit(query) => {
+                    self.finding_project = None;
+                    if query.is_empty() {
+                        self.projects.set_filter(None);
+                    }
+                }
+                InputEvent::Cancel => {
+                    self.finding_project = None;
+                    self.projects.set_filter(None);
+                }
+                InputEvent::Editing => self.projects.set_filter(Some(input.value.clone())),
+            }
+        }
+        self.sync_actions();
+    }
+
+    fn handle_search_key_event(&mut self, key_event: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        match search.input.handle_key_event(key_event) {
+            InputEvent::Submit(pattern) if pattern.is_empty() => self.search = None,
+            InputEvent::Submit(_) => search.editing = false,
+            InputEvent::Cancel => self.search = None,
+            InputEvent::Editing => {
+                search.update();
+                self.search_from_origin();
+            }
+        }
+    }
+
+    /// Jumps to the first match from where the search started, as the
+    /// pattern is typed.
+    fn search_from_origin(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let found = search.find(self.current_output(), search.origin, true);
+        self.show_match(found);
+    }
+
+    /// Jumps to the next or previous match.
+    fn jump_to_match(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let output = self.current_output();
+        let from = match search.current {
+            Some(current) if forward => current + 1,
+            Some(current) => current.saturating_sub(1),
+            None => output.first() + output.offset(usize::from(self.terminal_size.0)),
+        };
+        let found = search.find(output, from, forward);
+        self.show_match(found);
+    }
+
+    /// Makes `line` the current match and scrolls to it.
+    fn show_match(&mut self, line: Option<usize>) {
+        let index = match (&self.search, line) {
+            (Some(search), Some(line)) if search.filter => search
+                .matches(self.current_output())
+                .iter()
+                .position(|&n| n == line),
+            _ => None,
+        };
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.current = line;
+        let Some(line) = line else {
+            return;
+        };
+        match index {
+            Some(index) => search.scroll.to(index.saturating_sub(JUMP_CONTEXT)),
+            None => self
+                .output_mut()
+                .scroll_to(line.saturating_sub(JUMP_CONTEXT)),
+        }
+    }
+
+    /// Lists the tests in the background, `show_test_picker` gets them.
+    fn open_test_picker(&mut self) {
+        if self.listing_tests {
+            return;
+        }
+        self.listing_tests = true;
+        let (source_dir, build_dir) = (self.source_dir(), self.build_dir.clone());
+        let configure = self.configure.clone();
+        let tx = self.io.tests_tx.clone();
+        tokio::spawn(async move {
+            let tests = ctest::list(&source_dir, &build_dir, &configure).await;
+            tx.send(tests).await.ok();
+        });
+    }
+
+    fn show_test_picker(&mut self, tests: Result<Vec<ctest::Test>>) {
+        self.listing_tests = false;
+        match tests {
+            Ok(tests) => {
+                let all = ctest::Test {
+                    name: "All tests".to_string(),
+                    labels: vec![],
+                };
+                self.tests.list.items = std::iter::once(all).chain(tests).collect();
+                self.tests.list.state.select(Some(0));
+                self.picking_test = true;
+            }
+            Err(e) => self.show_error(format!("{e:#}")),
+        }
+    }
+
+    fn launch_test(&mut self, filter: ctest::Filter) {
+        self.picking_test = false;
+        if let Some(project) = self.projects.get_selected().cloned() {
+            self.launch(Action::Test(filter), project);
+        }
+    }
+
+    fn handle_test_key_event(&mut self, key_event: KeyEvent) {
+        if let Some((kind, prompt)) = &mut self.test_prompt {
+            match prompt.handle_key_event(key_event) {
+                InputEvent::Submit(value) => {
+                    let filter = match kind {
+                        TestPrompt::Regex => ctest::Filter::Regex(value),
+                        TestPrompt::Labels => ctest::Filter::Labels(
+                            value
+                                .split(',')
+                                .map(|l| l.trim().to_string())
+                                .filter(|l| !l.is_empty())
+                                .collect(),
+                        ),
+                    };
+                    self.test_prompt = None;
+                    self.launch_test(filter);
+                }
+                InputEvent::Cancel => self.test_prompt = None,
+                InputEvent::Editing => {}
+            }
+            return;
+        }
+
+        match self.config.keys.action(&key_event) {
+            Some(KeyAction::Up) => self.tests.prev(),
+            Some(KeyAction::Down) => self.tests.next(),
+            Some(KeyAction::Cancel) => self.picking_test = false,
+            Some(KeyAction::Search) => {
+                self.test_prompt = Some((
+                    TestPrompt::Regex,
+                    TextInput::new("Test regex", "").theme(self.config.theme),
+                ));
+            }
+            Some(KeyAction::Labels) => {
+                self.test_prompt = Some((
+                    TestPrompt::Labels,
+                    TextInput::new("Labels (comma separated)", "").theme(self.config.theme),
+                ));
+            }
+            Some(KeyAction::Select) => {
+                let filter = match self.tests.list.state.selected() {
+                    Some(0) | None => ctest::Filter::All,
+                    Some(_) => match self.tests.get_selected() {
+                        Some(test) => ctest::Filter::Name(test.name.clone()),
+                        None => ctest::Filter::All,
+                    },
+                };
+                self.launch_test(filter);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_build_dir_key_event(&mut self, key_event: KeyEvent) {
+        match self.config.keys.action(&key_event) {
+            Some(KeyAction::Up) => self.build_dirs.prev(),
+            Some(KeyAction::Down) => self.build_dirs.next(),
+            Some(KeyAction::Cancel | KeyAction::BuildDir) => self.picking_build_dir = false,
+            Some(KeyAction::Select) => {
+                self.picking_build_dir = false;
+                if let Some(build_dir) = self.build_dirs.get_selected() {
+                    self.build_dir = build_dir.path.clone();
+                    if let Err(e) = self.refresh_list() {
+                        self.show_error(format!("{e:#}"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the focus from Projects to Actions to Presets, skipping Presets
+    /// when the source dir has none.
+    fn focus_next(&mut self) {
+        let has_presets = !self.presets.list.items.is_empty();
+        (
+            self.projects.active,
+            self.actions.active,
+            self.presets.active,
+        ) = if self.projects.active {
+            (false, true, false)
+        } else if self.actions.active && has_presets {
+            (false, false, true)
+        } else {
+            (true, false, false)
+        };
+    }
+
+    /// Selects the highlighted configure preset, or deselects it when it is
+    /// already the active one.
+    fn apply_preset(&mut self) {
+        let Some(preset) = self.presets.get_selected().cloned() else {
+            return;
+        };
+        if self
+            .configure
+            .preset
+            .as_ref()
+            .is_some_and(|p| p.name == preset.name)
+        {
+            self.configure.preset = None;
+        } else {
+            if let Some(binary_dir) = &preset.binary_dir {
+                self.build_dir = binary_dir.clone();
+            }
+            self.configure.preset = Some(preset);
+        }
+        if let Err(e) = self.refresh_list() {
+            self.show_error(format!("{e:#}"));
+        }
+    }
+
+    fn open_build_dir_picker(&mut self) {
+        let source_dir = self.source_dir();
+        let mut items = build_dir::detect(&source_dir);
+        let index = match items.iter().position(|b| b.path == self.build_dir) {
+            Some(index) => index,
+            None => {
+                items.insert(0, BuildDir::new(self.build_dir.clone(), &source_dir));
+                0
+            }
+        };
+        self.build_dirs.list.items = items;
+        self.build_dirs.list.state.select(Some(index));
+        self.picking_build_dir = true;
     }
 
     fn refresh_list(&mut self) -> Result<()> {
-        self.projects.list.items = parser::refresh_list(&self.path)?;
+        match presets::load(&self.source_dir()) {
+            Ok(presets) => self.presets.list.items = presets,
+            Err(e) => self.show_error(format!("{e:#}")),
+        }
+
+        let selected = self
+            .projects
+            .get_selected()
+            .map(|p| p.target.clone())
+            .or_else(|| self.state.project.clone());
+        self.projects.list.items = project::discover(
+            &self.path,
+            &self.build_dir,
+            self.configure.configuration(),
+            &self.state.targets,
+        )?;
+        let index = selected
+            .and_then(|t| self.projects.list.items.iter().position(|p| p.target == t))
+            .unwrap_or(0);
+        self.projects.list.state.select(Some(index));
+        self.projects.refilter();
+        self.configure_reason = configure::needs_configure(&self.source_dir(), &self.build_dir);
+        self.sync_actions();
         Ok(())
     }
 
+    /// Offers only the actions that apply to the selected project, keeping
+    /// the selected action when it is still available.
+    fn sync_actions(&mut self) {
+        let selected = self
+            .actions
+            .get_selected()
+            .map(|a| a.to_str().to_string())
+            .or_else(|| self.state.action.clone());
+        let items = match self.projects.get_selected() {
+            Some(project) => Action::available_for(project.kind),
+            None => vec![],
+        };
+        let index = selected
+            .and_then(|a| items.iter().position(|i| i.to_str() == a))
+            .unwrap_or(0);
+        self.actions.list.items = items;
+        self.actions.list.state.select(Some(index));
+    }
+
+    fn source_dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default()
+    }
+
     fn cmake_configure(&mut self) -> Result<()> {
-        self.output.clear();
-        let out_tx = self.io.out_tx.clone();
-        let path = self.path.clone();
-        tokio::spawn(async move {
-            if let Err(e) = utils::spawn_command(
-                &out_tx,
-                None,
-                "cmake",
-                &["--build", "build"],
-                path.parent().unwrap().to_str().unwrap(),
-                "Build",
-            )
-            .await
-            {
-                out_tx.send(Err(e)).await.ok();
-            }
+        if self.jobs.list.items.iter().any(Job::is_running) {
+            self.show_error("A job is running, stop it before configuring");
+            return Ok(());
+        }
+        self.configure_reason = None;
+        let source_dir = self.source_dir();
+        let build_dir = self.build_dir.clone();
+        let configure = self.configure.clone();
+        self.spawn_job(CONFIGURE_JOB.to_string(), None, |out| async move {
+            configure.run(&out, &source_dir, &build_dir).await
         });
         Ok(())
     }
@@ -199,52 +1104,340 @@ impl App {
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let projects_area = Rect {
+        let presets_height = match self.presets.list.items.len() {
+            0 => 0,
+            n => (n as u16 + 2).min(area.height / 4),
+        };
+        let presets_area = Rect {
             x: area.x + 1,
             y: area.y,
             width: area.width / 4,
-            height: area.height / 2,
+            height: presets_height,
         };
-        ListBox::<Project>::new(" Projects ").render(projects_area, buf, &mut self.projects);
+        if presets_height > 0 {
+            ListBox::<ConfigurePreset>::new(" Presets ")
+                .theme(self.config.theme)
+                .render(presets_area, buf, &mut self.presets);
+        }
+
+        let projects_area = Rect {
+            x: area.x + 1,
+            y: area.y + presets_height,
+            width: area.width / 4,
+            height: area.height / 2 - presets_height,
+        };
+        ListBox::<Project>::new(" Projects ")
+            .theme(self.config.theme)
+            .render(projects_area, buf, &mut self.projects);
+        if let Some(input) = &self.finding_project {
+            let prompt_area = Rect {
+                y: (projects_area.y + projects_area.height).saturating_sub(4),
+                height: 3.min(projects_area.height),
+                ..projects_area.inner(Margin::new(1, 0))
+            };
+            input.render(prompt_area, buf);
+        }
 
         let actions_area = Rect {
             x: area.x + 1,
-            y: projects_area.y + projects_area.height,
+            y: area.y + area.height / 2,
             width: area.width / 4,
             height: area.height / 2,
         };
-        ListBox::<Action>::new(" Actions ").render(actions_area, buf, &mut self.actions);
+        let no_debugger = self.configure.debugger.is_none();
+        ListBox::<Action>::new(" Actions ")
+            .theme(self.config.theme)
+            .disabled(move |action| no_debugger && *action == Action::Debug)
+            .render(actions_area, buf, &mut self.actions);
 
-        let out_area = Rect {
+        let tabs_area = Rect {
             x: projects_area.x + projects_area.width,
             y: area.y,
             width: area.width / 4 * 3 - 1,
-            height: area.height - 1,
+            height: 1,
         };
+        let out_area = Rect {
+            y: area.y + 1,
+            height: area.height.saturating_sub(2),
+            ..tabs_area
+        };
+        let theme = self.config.theme;
+        let titles = std::iter::once(Line::from(" Messages ")).chain(
+            self.jobs.list.items.iter().map(|job| {
+                Line::from(vec![
+                    job.status().icon(),
+                    Span::raw(format!(" {}", job.title)),
+                ])
+            }),
+        );
+        Tabs::new(titles)
+            .select(self.tab)
+            .style(Style::new().fg(theme.border))
+            .highlight_style(Style::new().fg(theme.active_border).bold().reversed())
+            .divider("│")
+            .padding(" ", " ")
+            .render(tabs_area, buf);
+
         let block = if self.input {
             Block::bordered()
-                .border_style(Style::new().light_yellow())
+                .border_style(Style::new().fg(theme.input_border))
                 .title(" Output ")
         } else {
             Block::bordered()
-                .border_style(Style::new().gray())
+                .border_style(Style::new().fg(theme.border))
                 .title(" Output ")
         };
-        let lines: Vec<_> = self
-            .output
+        let build_dir = BuildDir::new(self.build_dir.clone(), &self.source_dir());
+        let mut settings = Line::from(format!(" {} ", self.configure.describe()));
+        if let Some(reason) = self.configure_reason {
+            settings.push_span(
+                Span::raw(format!("configure needed: {} ", reason.to_str())).light_yellow(),
+            );
+        }
+        if !self.queued.is_empty() {
+            let queued: Vec<_> = self
+                .queued
+                .iter()
+                .map(|(action, project, _)| job_title(action, project))
+                .collect();
+            settings.push_span(Span::raw(format!("queued: {} ", queued.join(", "))).light_yellow());
+        }
+        let following = match self.current_job() {
+            Some(job) => job.output.is_following(),
+            None => self.output.is_following(),
+        };
+        if !following {
+            settings.push_span(Span::raw("scrolled ").fg(theme.detail));
+        }
+        if let Some(search) = &self.search {
+            settings.push_span(search_status(search, self.current_output()).fg(theme.highlight));
+        }
+        if let Some(summary) = self.current_job().and_then(|j| j.test_summary.as_ref()) {
+            settings.push_span(Span::raw(format!("{} ", summary.describe())).light_cyan());
+        }
+        let problems = self
+            .current_job()
+            .filter(|j| !j.problems.list.items.is_empty());
+        let (output_area, problems_area) = match problems {
+            Some(job) => {
+                let height = (job.problems.list.items.len() as u16 + 2)
+                    .min(out_area.height / 3)
+                    .max(3);
+                let [output, problems] =
+                    Layout::vertical([Constraint::Fill(1), Constraint::Length(height)])
+                        .areas(out_area);
+                (output, Some(problems))
+            }
+            None => (out_area, None),
+        };
+        let block = block
+            .title_bottom(settings)
+            .title_bottom(Line::from(format!(" {} ", build_dir.label())).right_aligned());
+        let inner = block.inner(output_area);
+        let (rows, cols) = (inner.height.max(1), inner.width.max(1));
+        self.terminal_size = (rows, cols);
+        for terminal in self
+            .jobs
+            .list
+            .items
             .iter()
-            .flat_map(|text| {
-                text.lines.clone().into_iter().map(|mut line| {
-                    let mut new_spans = vec![Span::raw(" ")];
-                    new_spans.extend(line.spans.into_iter());
-                    line.spans = new_spans;
-                    line.patch_style(text.style)
-                })
-            })
-            .collect();
-        Paragraph::new(lines)
-            .block(block)
-            .scroll((self.offset, 0))
-            .render(out_area, buf);
+            .filter_map(|j| j.terminal.as_ref())
+        {
+            terminal.resize(rows, cols);
+        }
+        let terminal = self
+            .current_job()
+            .and_then(|j| j.terminal.as_ref())
+            .filter(|t| t.is_active());
+        match terminal {
+            Some(terminal) => {
+                let terminal = terminal.screen();
+                let screen = terminal.parser.screen();
+                Paragraph::new(pty::lines(screen))
+                    .block(block)
+                    .render(output_area, buf);
+                let (row, col) = screen.cursor_position();
+                let visible = row < inner.height && col < inner.width;
+                if self.input && visible && !screen.hide_cursor() {
+                    buf[(inner.x + col, inner.y + row)]
+                        .set_style(Style::new().add_modifier(Modifier::REVERSED));
+                }
+            }
+            None => {
+                let output = self.current_output();
+                let rows = usize::from(inner.height);
+                let search = self.search.as_ref();
+                let filtered = search.filter(|s| s.filter).map(|s| (s, s.matches(output)));
+                let (height, offset) = match &filtered {
+                    Some((search, matches)) => {
+                        (matches.len(), search.scroll.offset(matches.len(), rows))
+                    }
+                    None => (output.height(), output.offset(rows)),
+                };
+                let visible: Vec<_> = match &filtered {
+                    Some((_, matches)) => matches
+                        .iter()
+                        .skip(offset)
+                        .take(rows)
+                        .filter_map(|&n| Some((n, output.get(n)?)))
+                        .collect(),
+                    None => output.numbered().skip(offset).take(rows).collect(),
+                };
+                // The lines with a problem are marked in the gutter, the
+                // selected one reversed.
+                let mut marks = BTreeMap::new();
+                let mut selected_problem = None;
+                if let Some(job) = self.current_job() {
+                    for diagnostic in job.problems.list.items.iter().rev() {
+                        marks.insert(diagnostic.output_line, diagnostic.severity);
+                    }
+                    selected_problem = job.problems.get_selected().map(|d| d.output_line);
+                }
+                let lines: Vec<_> = visible
+                    .into_iter()
+                    .map(|(i, line)| {
+                        let mut line = line.clone();
+                        if let Some(search) = search
+                            && let Some(regex) = search.regex()
+                        {
+                            let style = match search.current == Some(i) {
+                                true => search::CURRENT,
+                                false => search::MATCH,
+                            };
+                            line = search::highlight(line, regex, style);
+                        }
+                        let gutter = match marks.get(&i) {
+                            Some(severity) if selected_problem == Some(i) => {
+                                line = line.bold();
+                                Span::raw("▌").fg(severity.color()).reversed()
+                            }
+                            Some(severity) => Span::raw("▌").fg(severity.color()),
+                            None => Span::raw(" "),
+                        };
+                        line.spans.insert(0, gutter);
+                        line
+                    })
+                    .collect();
+                Paragraph::new(lines).block(block).render(output_area, buf);
+
+                let max_offset = height.saturating_sub(rows);
+                if max_offset > 0 {
+                    let mut scrollbar = ScrollbarState::new(max_offset + 1)
+                        .position(offset)
+                        .viewport_content_length(rows);
+                    Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                        .begin_symbol(None)
+                        .end_symbol(None)
+                        .track_style(Style::new().fg(theme.border))
+                        .thumb_style(Style::new().fg(theme.active_border))
+                        .render(output_area.inner(Margin::new(0, 1)), buf, &mut scrollbar);
+                }
+            }
+        }
+
+        if let Some(area) = problems_area
+            && let Some(job) = self.current_job_mut()
+        {
+            let (errors, warnings) = job.problem_counts();
+            ListBox::<Diagnostic>::new(format!(
+                " Problems ({}) ",
+                job::describe_counts(errors, warnings)
+            ))
+            .theme(theme)
+            .render(area, buf, &mut job.problems);
+        }
+
+        if let Some(search) = self.search.as_ref().filter(|s| s.editing) {
+            let prompt_area = Rect {
+                y: (output_area.y + output_area.height).saturating_sub(4),
+                height: 3.min(output_area.height),
+                ..output_area.inner(Margin::new(1, 0))
+            };
+            search.input.render(prompt_area, buf);
+        }
+
+        if self.picking_job {
+            let picker_area = popup_area(out_area);
+            Clear.render(picker_area, buf);
+            let hints = self
+                .config
+                .keys
+                .hints(&[(KeyAction::Select, "show"), (KeyAction::Stop, "stop")]);
+            ListBox::<Job>::new(format!(" Jobs ({hints}) "))
+                .theme(self.config.theme)
+                .render(picker_area, buf, &mut self.jobs);
+        }
+
+        if self.picking_build_dir {
+            let picker_area = popup_area(out_area);
+            Clear.render(picker_area, buf);
+            ListBox::<BuildDir>::new(" Build directory ")
+                .theme(self.config.theme)
+                .render(picker_area, buf, &mut self.build_dirs);
+        }
+
+        if self.picking_test {
+            let picker_area = popup_area(out_area);
+            Clear.render(picker_area, buf);
+            let hints = self.config.keys.hints(&[
+                (KeyAction::Select, "run"),
+                (KeyAction::Search, "regex"),
+                (KeyAction::Labels, "labels"),
+            ]);
+            ListBox::<ctest::Test>::new(format!(" Tests ({hints}) "))
+                .theme(self.config.theme)
+                .render(picker_area, buf, &mut self.tests);
+            if let Some((_, prompt)) = &self.test_prompt {
+                prompt.render(prompt_area(out_area), buf);
+            }
+        }
+
+        if let Some(editor) = &self.run_editor {
+            let editor_area = Rect {
+                height: 11.min(out_area.height),
+                ..popup_area(out_area)
+            };
+            editor.render(editor_area, buf);
+        }
+    }
+}
+
+/// E.g. `/warning 3/17 `, or `filter /warning 17 lines `.
+fn search_status(search: &Search, output: &LineBuffer) -> Span<'static> {
+    let pattern = search.pattern();
+    if search.invalid {
+        return Span::raw(format!("/{pattern} invalid regex ")).light_red();
+    }
+    if search.regex().is_none() {
+        return Span::raw("/ ");
+    }
+    let matches = search.matches(output);
+    if search.filter {
+        return Span::raw(format!("filter /{pattern} {} lines ", matches.len()));
+    }
+    match search
+        .current
+        .and_then(|c| matches.iter().position(|&n| n == c))
+    {
+        Some(index) => Span::raw(format!("/{pattern} {}/{} ", index + 1, matches.len())),
+        None => Span::raw(format!("/{pattern} {} matches ", matches.len())),
+    }
+}
+
+fn job_title(action: &Action, project: &Project) -> String {
+    format!("{} {}", action.to_str(), project.target)
+}
+
+fn popup_area(area: Rect) -> Rect {
+    area.inner(Margin::new(area.width / 4, area.height / 4))
+}
+
+/// A one line prompt placed over the bottom of `area`.
+fn prompt_area(area: Rect) -> Rect {
+    let popup = popup_area(area);
+    Rect {
+        y: (popup.y + popup.height).saturating_sub(3),
+        height: 3.min(popup.height),
+        ..popup
     }
 }
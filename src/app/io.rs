@@ -1,15 +1,27 @@
 use eyre::Result;
-use ratatui::text::Text;
 use tokio::sync::mpsc;
 
+use crate::{ctest::Test, job::Chunk};
+
+/// Output of the jobs, tagged with the id of the job, and the results of
+/// the background work the interface waits for.
 pub struct AppIo {
-    pub out_tx: mpsc::Sender<Result<Text<'static>>>,
-    pub out_rx: mpsc::Receiver<Result<Text<'static>>>,
+    pub out_tx: mpsc::Sender<(usize, Result<Chunk>)>,
+    pub out_rx: mpsc::Receiver<(usize, Result<Chunk>)>,
+    /// The tests listed for the test picker.
+    pub tests_tx: mpsc::Sender<Result<Vec<Test>>>,
+    pub tests_rx: mpsc::Receiver<Result<Vec<Test>>>,
 }
 
 impl Default for AppIo {
     fn default() -> Self {
         let (out_tx, out_rx) = mpsc::channel(50);
-        Self { out_tx, out_rx }
+        let (tests_tx, tests_rx) = mpsc::channel(1);
+        Self {
+            out_tx,
+            out_rx,
+            tests_tx,
+            tests_rx,
+        }
     }
 }
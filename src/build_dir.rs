@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::list_box::ListItemProvider;
+
+/// How deep below the source directory to look for configured build trees,
+/// enough to find layouts such as `out/clang`.
+const SEARCH_DEPTH: usize = 2;
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct BuildDir {
+    pub path: PathBuf,
+    label: String,
+}
+
+impl BuildDir {
+    pub fn new(path: PathBuf, source_dir: &Path) -> Self {
+        let label = path
+            .strip_prefix(source_dir)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        Self { path, label }
+    }
+
+    /// The path relative to the source directory, for display.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.path.join("CMakeCache.txt").is_file()
+    }
+}
+
+impl ListItemProvider for BuildDir {
+    fn as_str(&self) -> &str {
+        self.label()
+    }
+
+    fn detail(&self) -> Option<String> {
+        (!self.is_configured()).then(|| "not configured".to_string())
+    }
+}
+
+/// Finds the configured build trees, i.e. directories holding a
+/// `CMakeCache.txt`, below `source_dir`.
+pub fn detect(source_dir: &Path) -> Vec<BuildDir> {
+    let mut found = Vec::new();
+    search(source_dir, SEARCH_DEPTH, &mut found);
+    found.sort();
+    found
+        .into_iter()
+        .map(|path| BuildDir::new(path, source_dir))
+        .collect()
+}
+
+fn search(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || !path.is_dir() {
+            continue;
+        }
+        if path.join("CMakeCache.txt").is_file() {
+            found.push(path);
+        } else if depth > 1 {
+            search(&path, depth - 1, found);
+        }
+    }
+}
+
+/// Picks the build directory to start with: the one given on the command
+/// line, else `build` when it exists, else the first detected tree.
+pub fn initial(source_dir: &Path, requested: Option<PathBuf>) -> PathBuf {
+    if let Some(requested) = requested {
+        return requested;
+    }
+    let default = source_dir.join("build");
+    if default.exists() {
+        return default;
+    }
+    detect(source_dir)
+        .into_iter()
+        .next()
+        .map(|b| b.path)
+        .unwrap_or(default)
+}
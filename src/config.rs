@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+use crate::{line_buffer, log::LogFormat};
+
+pub mod keys;
+pub mod theme;
+use keys::Keymap;
+use theme::Theme;
+
+const CONFIG_FILE: &str = "cmake-runner/config.toml";
+const REPO_CONFIG_FILE: &str = ".cmake-runner/config.toml";
+
+/// User settings, read from the global config file and overridden by the one
+/// in the source directory.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: Keymap,
+    pub theme: Theme,
+    pub commands: Commands,
+    pub output: OutputSettings,
+}
+
+/// How the output pane keeps the output of the jobs.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    /// The number of lines kept per job, the oldest are dropped first.
+    pub scrollback: usize,
+    /// Saves the output of every job to `.cmake-runner/logs` when set.
+    pub tee: Option<LogFormat>,
+    /// The number of logs kept there, the oldest are deleted first.
+    pub logs: usize,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            scrollback: line_buffer::SCROLLBACK,
+            tee: None,
+            logs: 20,
+        }
+    }
+}
+
+/// The programs to invoke, either names looked up in the `PATH` or paths.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Commands {
+    pub cmake: String,
+    pub ctest: String,
+    /// gdb or lldb, detected when unset.
+    pub debugger: Option<String>,
+    /// A terminal to start the debugger in, e.g. `alacritty -e`.
+    pub terminal: Option<String>,
+    /// The editor to open files in, `$VISUAL` or `$EDITOR` when unset.
+    pub editor: Option<String>,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self {
+            cmake: "cmake".to_string(),
+            ctest: "ctest".to_string(),
+            debugger: None,
+            terminal: None,
+            editor: None,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/cmake-runner/config.toml`, falling back to
+/// `~/.config/cmake-runner/config.toml`.
+pub fn global_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(config_home.join(CONFIG_FILE))
+}
+
+/// Loads the configuration for `source_dir`. Tables in the per-repo file are
+/// merged into the global ones, other values replace them.
+pub fn load(source_dir: &Path) -> Result<Config> {
+    let mut table = toml::Table::new();
+    let files = global_path()
+        .into_iter()
+        .chain([source_dir.join(REPO_CONFIG_FILE)]);
+    for path in files.filter(|p| p.is_file()) {
+        let file = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        let overrides: toml::Table = toml::from_str(&file)
+            .wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+        merge(&mut table, overrides);
+    }
+    Config::deserialize(table).wrap_err("invalid configuration")
+}
+
+fn merge(table: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(overrides)) => {
+                merge(table, overrides)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
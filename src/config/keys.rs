@@ -0,0 +1,374 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use eyre::{Result, eyre};
+use serde::Deserialize;
+
+/// Everything a key can be bound to, named as in the `[keys]` table.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    Quit,
+    Refresh,
+    Configure,
+    BuildDir,
+    EditRun,
+    Generator,
+    BuildType,
+    Focus,
+    Up,
+    Down,
+    Select,
+    Cancel,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollTop,
+    ScrollBottom,
+    Input,
+    Search,
+    Filter,
+    NextMatch,
+    PrevMatch,
+    Labels,
+    Stop,
+    Restart,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    Jobs,
+    Problems,
+    NextProblem,
+    PrevProblem,
+    Edit,
+    FindProject,
+    SaveLog,
+    SaveAnsiLog,
+}
+
+/// The name of the action in the `[keys]` table, e.g. `scroll_down`.
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, c) in format!("{self:?}").chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                f.write_str("_")?;
+            }
+            write!(f, "{}", c.to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl KeyAction {
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            KeyAction::Quit => &["ctrl+c"],
+            KeyAction::Refresh => &["shift+r"],
+            KeyAction::Configure => &["ctrl+b"],
+            KeyAction::BuildDir => &["d"],
+            KeyAction::EditRun => &["e"],
+            KeyAction::Generator => &["g"],
+            KeyAction::BuildType => &["t"],
+            KeyAction::Focus => &["a"],
+            KeyAction::Up => &["up"],
+            KeyAction::Down => &["down"],
+            KeyAction::Select => &["enter"],
+            KeyAction::Cancel => &["esc"],
+            KeyAction::ScrollUp => &["k"],
+            KeyAction::ScrollDown => &["j"],
+            KeyAction::PageUp => &["pageup"],
+            KeyAction::PageDown => &["pagedown"],
+            KeyAction::ScrollTop => &["home"],
+            KeyAction::ScrollBottom => &["end"],
+            KeyAction::Input => &["alt+i"],
+            KeyAction::Search => &["/"],
+            KeyAction::Filter => &["f"],
+            KeyAction::NextMatch => &["n"],
+            KeyAction::PrevMatch => &["shift+n"],
+            KeyAction::Labels => &["l"],
+            KeyAction::Stop => &["x"],
+            KeyAction::Restart => &["r"],
+            KeyAction::NextTab => &["]"],
+            KeyAction::PrevTab => &["["],
+            KeyAction::CloseTab => &["w"],
+            KeyAction::Jobs => &["shift+j"],
+            KeyAction::Problems => &["p"],
+            KeyAction::NextProblem => &["f8"],
+            KeyAction::PrevProblem => &["shift+f8"],
+            KeyAction::Edit => &["o"],
+            KeyAction::FindProject => &["ctrl+p"],
+            KeyAction::SaveLog => &["s"],
+            KeyAction::SaveAnsiLog => &["shift+s"],
+        }
+    }
+
+    const ALL: [KeyAction; 37] = [
+        KeyAction::Quit,
+        KeyAction::Refresh,
+        KeyAction::Configure,
+        KeyAction::BuildDir,
+        KeyAction::EditRun,
+        KeyAction::Generator,
+        KeyAction::BuildType,
+        KeyAction::Focus,
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::Select,
+        KeyAction::Cancel,
+        KeyAction::ScrollUp,
+        KeyAction::ScrollDown,
+        KeyAction::PageUp,
+        KeyAction::PageDown,
+        KeyAction::ScrollTop,
+        KeyAction::ScrollBottom,
+        KeyAction::Input,
+        KeyAction::Search,
+        KeyAction::Filter,
+        KeyAction::NextMatch,
+        KeyAction::PrevMatch,
+        KeyAction::Labels,
+        KeyAction::Stop,
+        KeyAction::Restart,
+        KeyAction::NextTab,
+        KeyAction::PrevTab,
+        KeyAction::CloseTab,
+        KeyAction::Jobs,
+        KeyAction::Problems,
+        KeyAction::NextProblem,
+        KeyAction::PrevProblem,
+        KeyAction::Edit,
+        KeyAction::FindProject,
+        KeyAction::SaveLog,
+        KeyAction::SaveAnsiLog,
+    ];
+}
+
+/// A key with its modifiers, written like `ctrl+b`, `shift+r`, `alt+i`,
+/// `pagedown` or `/`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Folds the different ways terminals report shifted keys: an upper case
+    /// letter always carries SHIFT, a shifted symbol never does.
+    fn normalize(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if c.is_uppercase() => {
+                modifiers |= KeyModifiers::SHIFT;
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            KeyCode::Char(c) if !c.is_alphabetic() => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::Char(c)
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn matches(&self, key_event: &KeyEvent) -> bool {
+        *self == Self::normalize(key_event.code, key_event.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // A trailing `+` is the plus key itself, as in `ctrl++`.
+        let (mods, key) = match s.strip_suffix("++") {
+            Some(mods) => (mods, "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in mods.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(eyre!("unknown modifier `{modifier}` in `{s}`")),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(eyre!("unknown key `{key}` in `{s}`")),
+                },
+            },
+        };
+        Ok(Self::normalize(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{}", format!("{code:?}").to_ascii_lowercase()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The key bindings: the defaults, with the actions named in the config file
+/// rebound.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "BTreeMap<KeyAction, Keys>")]
+pub struct Keymap {
+    bindings: BTreeMap<KeyAction, Vec<KeyBinding>>,
+}
+
+impl Keymap {
+    /// The action bound to `key_event`; a key is bound to one action at most.
+    pub fn action(&self, key_event: &KeyEvent) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(key_event)))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn is(&self, action: KeyAction, key_event: &KeyEvent) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|keys| keys.iter().any(|k| k.matches(key_event)))
+    }
+
+    /// Describes `hints` with the first key of each action, e.g.
+    /// `enter show, x stop`, leaving out the unbound ones.
+    pub fn hints(&self, hints: &[(KeyAction, &str)]) -> String {
+        hints
+            .iter()
+            .filter_map(|(action, hint)| {
+                let key = self.bindings.get(action)?.first()?;
+                Some(format!("{key} {hint}"))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KeyAction::ALL
+            .iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|k| k.parse().expect("valid default key"))
+                    .collect();
+                (*action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl TryFrom<BTreeMap<KeyAction, Keys>> for Keymap {
+    type Error = eyre::Report;
+
+    /// Rebinds the overridden actions. Their keys are taken away from the
+    /// default bindings of other actions, and a key overridden for two
+    /// actions is an error.
+    fn try_from(overrides: BTreeMap<KeyAction, Keys>) -> Result<Self> {
+        let mut overridden: BTreeMap<KeyAction, Vec<KeyBinding>> = BTreeMap::new();
+        for (action, keys) in overrides {
+            let keys = match keys {
+                Keys::One(key) => vec![key],
+                Keys::Many(keys) => keys,
+            };
+            let keys: Vec<_> = keys.iter().map(|k| k.parse()).collect::<Result<_>>()?;
+            for key in &keys {
+                if let Some((other, _)) = overridden
+                    .iter()
+                    .find(|(_, other_keys)| other_keys.contains(key))
+                {
+                    return Err(eyre!("`{key}` is bound to both `{other}` and `{action}`"));
+                }
+            }
+            overridden.insert(action, keys);
+        }
+
+        let mut keymap = Self::default();
+        for keys in keymap.bindings.values_mut() {
+            keys.retain(|key| !overridden.values().flatten().any(|k| k == key));
+        }
+        keymap.bindings.extend(overridden);
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn overrides_take_keys_from_defaults() {
+        let keymap: Keymap = toml::from_str(r#"stop = "j""#).unwrap();
+        assert_eq!(keymap.action(&key('j')), Some(KeyAction::Stop));
+        assert_eq!(keymap.action(&key('x')), None);
+        assert!(!keymap.is(KeyAction::ScrollDown, &key('j')));
+        assert_eq!(keymap.action(&key('k')), Some(KeyAction::ScrollUp));
+    }
+
+    #[test]
+    fn rejects_a_key_overridden_twice() {
+        let error = toml::from_str::<Keymap>("stop = \"q\"\nquit = [\"ctrl+c\", \"q\"]")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`q` is bound to both `quit` and `stop`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn names_actions_like_the_config() {
+        assert_eq!(KeyAction::ScrollDown.to_string(), "scroll_down");
+        assert_eq!(KeyAction::Quit.to_string(), "quit");
+    }
+}
@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// The colors of the list boxes and the output pane. Colors are names such
+/// as `light_blue`, indexes such as `208` or hex codes such as `#ff8800`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// The border of unfocused boxes.
+    #[serde(deserialize_with = "color")]
+    pub border: Color,
+    /// The border of the focused box and popups.
+    #[serde(deserialize_with = "color")]
+    pub active_border: Color,
+    /// The border of the output pane while typing into the running program.
+    #[serde(deserialize_with = "color")]
+    pub input_border: Color,
+    /// The selected list item.
+    #[serde(deserialize_with = "color")]
+    pub highlight: Color,
+    /// The extra information after list items.
+    #[serde(deserialize_with = "color")]
+    pub detail: Color,
+    /// What processes print to stderr.
+    #[serde(deserialize_with = "color")]
+    pub stderr: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: Color::Gray,
+            active_border: Color::LightBlue,
+            input_border: Color::LightYellow,
+            highlight: Color::LightGreen,
+            detail: Color::DarkGray,
+            stderr: Color::LightMagenta,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name).map_err(|_| D::Error::custom(format!("invalid color `{name}`")))
+}
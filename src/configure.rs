@@ -0,0 +1,250 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use eyre::Result;
+
+use crate::{
+    config::Commands, debugger::Debugger, file_api, job::Output, presets::ConfigurePreset, utils,
+};
+
+pub const BUILD_TYPES: [&str; 4] = ["Debug", "Release", "RelWithDebInfo", "MinSizeRel"];
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generator {
+    #[default]
+    Ninja,
+    UnixMakefiles,
+}
+
+impl Generator {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Generator::Ninja => "Ninja",
+            Generator::UnixMakefiles => "Unix Makefiles",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ninja" => Some(Generator::Ninja),
+            "unix makefiles" | "make" | "makefiles" => Some(Generator::UnixMakefiles),
+            _ => None,
+        }
+    }
+
+    /// Ninja when it is installed, Unix Makefiles otherwise.
+    pub fn detect() -> Self {
+        match utils::which("ninja") {
+            Some(_) => Generator::Ninja,
+            None => Generator::UnixMakefiles,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Generator::Ninja => Generator::UnixMakefiles,
+            Generator::UnixMakefiles => Generator::Ninja,
+        }
+    }
+}
+
+/// Why a build tree has to be (re)configured before building.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    NotConfigured,
+    ListsChanged,
+}
+
+impl Reason {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Reason::NotConfigured => "not configured",
+            Reason::ListsChanged => "CMake files changed",
+        }
+    }
+}
+
+/// The settings passed to `cmake -S <source> -B <build>`, or the preset that
+/// replaces them.
+#[derive(Clone, Debug)]
+pub struct Configure {
+    pub generator: Generator,
+    pub build_type: String,
+    pub cache_entries: Vec<(String, String)>,
+    pub preset: Option<ConfigurePreset>,
+    pub commands: Commands,
+    pub debugger: Option<Debugger>,
+}
+
+impl Default for Configure {
+    fn default() -> Self {
+        Self {
+            generator: Generator::detect(),
+            build_type: BUILD_TYPES[0].to_string(),
+            cache_entries: vec![],
+            preset: None,
+            commands: Commands::default(),
+            debugger: None,
+        }
+    }
+}
+
+impl Configure {
+    pub fn next_build_type(&mut self) {
+        let index = BUILD_TYPES
+            .iter()
+            .position(|t| *t == self.build_type)
+            .map_or(0, |i| (i + 1) % BUILD_TYPES.len());
+        self.build_type = BUILD_TYPES[index].to_string();
+    }
+
+    pub fn args(&self, source_dir: &Path, build_dir: &Path) -> Vec<String> {
+        if let Some(preset) = &self.preset {
+            let mut args = vec!["--preset".to_string(), preset.name.clone()];
+            args.extend(self.cache_entries.iter().map(|(k, v)| format!("-D{k}={v}")));
+            return args;
+        }
+
+        let mut args = vec![
+            "-S".to_string(),
+            source_dir.to_string_lossy().into_owned(),
+            "-B".to_string(),
+            build_dir.to_string_lossy().into_owned(),
+        ];
+        // CMake refuses to switch the generator of an existing build tree.
+        // Actions only configure new trees, so an existing one only gets the
+        // build type from an explicit configure.
+        if !build_dir.join("CMakeCache.txt").is_file() {
+            args.extend(["-G".to_string(), self.generator.to_str().to_string()]);
+        }
+        args.push(format!("-DCMAKE_BUILD_TYPE={}", self.build_type));
+        args.extend(self.cache_entries.iter().map(|(k, v)| format!("-D{k}={v}")));
+        args
+    }
+
+    /// Arguments for `cmake` to build `target`, or everything when `None`,
+    /// through the build preset linked to the selected configure preset when
+    /// there is one.
+    pub fn build_args(&self, build_dir: &Path, target: Option<&str>) -> Vec<String> {
+        let mut args = match self.preset.as_ref().and_then(|p| p.build_preset.as_ref()) {
+            Some(build_preset) => vec![
+                "--build".to_string(),
+                "--preset".to_string(),
+                build_preset.clone(),
+            ],
+            // Single-config generators ignore `--config`.
+            None => vec![
+                "--build".to_string(),
+                build_dir.to_string_lossy().into_owned(),
+                "--config".to_string(),
+                self.build_type.clone(),
+            ],
+        };
+        if let Some(target) = target {
+            args.extend(["-t".to_string(), target.to_string()]);
+        }
+        args
+    }
+
+    /// `env` over the environment of the selected preset, which
+    /// `cmake --preset` only sets for the configure step.
+    pub fn env(&self, env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut merged = self
+            .preset
+            .as_ref()
+            .map(|p| p.environment.clone())
+            .unwrap_or_default();
+        merged.extend(env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+
+    /// The configuration built in a multi-config build tree: the one of the
+    /// build preset, or else the build type.
+    pub fn configuration(&self) -> &str {
+        self.preset
+            .as_ref()
+            .and_then(|p| p.configuration.as_deref())
+            .unwrap_or(&self.build_type)
+    }
+
+    /// A short description for the output pane footer.
+    pub fn describe(&self) -> String {
+        match &self.preset {
+            Some(preset) => format!("preset {}", preset.name),
+            None => format!("{} · {}", self.generator.to_str(), self.build_type),
+        }
+    }
+
+    pub async fn run(&self, out: &Output, source_dir: &Path, build_dir: &Path) -> Result<()> {
+        file_api::write_query(build_dir)?;
+        let args = self.args(source_dir, build_dir);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        utils::spawn_command(
+            out,
+            &self.commands.cmake,
+            &args,
+            &source_dir.to_string_lossy(),
+            "Configure",
+        )
+        .await
+    }
+}
+
+/// Parses a `-D` cache entry, `NAME=VALUE` or `NAME:TYPE=VALUE`.
+pub fn parse_cache_entry(entry: &str) -> Option<(String, String)> {
+    let (name, value) = entry.split_once('=')?;
+    (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+}
+
+/// Checks whether `build_dir` must be configured: either it has no
+/// `CMakeCache.txt` or a CMake file in `source_dir` is newer than it.
+pub fn needs_configure(source_dir: &Path, build_dir: &Path) -> Option<Reason> {
+    let Some(configured) = modified(&build_dir.join("CMakeCache.txt")) else {
+        return Some(Reason::NotConfigured);
+    };
+    let mut files = Vec::new();
+    cmake_files(source_dir, build_dir, &mut files);
+    files
+        .iter()
+        .filter_map(|f| modified(f))
+        .any(|modified| modified > configured)
+        .then_some(Reason::ListsChanged)
+}
+
+/// Collects `CMakeLists.txt`, `*.cmake` and preset files, skipping hidden directories,
+/// build trees and symlinks, which may point back up the tree.
+fn cmake_files(dir: &Path, build_dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if file_type.is_symlink() {
+            continue;
+        } else if file_type.is_dir() {
+            if !name.starts_with('.') && path != build_dir && !path.join("CMakeCache.txt").exists()
+            {
+                cmake_files(&path, build_dir, files);
+            }
+        } else if name == "CMakeLists.txt"
+            || name.ends_with(".cmake")
+            || name == "CMakePresets.json"
+            || name == "CMakeUserPresets.json"
+        {
+            files.push(path);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
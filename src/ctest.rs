@@ -0,0 +1,170 @@
+use std::{path::Path, sync::LazyLock};
+
+use eyre::{Result, WrapErr, eyre};
+use regex::Regex;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::{configure::Configure, list_box::ListItemProvider};
+
+/// Matches the per-test result lines, e.g.
+/// `  3/12 Test  #3: parser_test ......***Failed    0.02 sec`.
+static RESULT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*\d+/\d+\s+Test\s+#\d+:\s+\S+\s+\.*\s*(?:\*{3})?(Passed|Failed|Timeout|Not Run|Skipped|Exception)")
+        .expect("valid regex")
+});
+
+/// Which tests to run.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    All,
+    Name(String),
+    Regex(String),
+    Labels(Vec<String>),
+}
+
+impl Filter {
+    fn args(&self) -> Vec<String> {
+        match self {
+            Filter::All => vec![],
+            Filter::Name(name) => vec!["-R".to_string(), format!("^{}$", regex::escape(name))],
+            Filter::Regex(regex) => vec!["-R".to_string(), regex.clone()],
+            Filter::Labels(labels) => labels
+                .iter()
+                .flat_map(|l| ["-L".to_string(), l.clone()])
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Test {
+    pub name: String,
+    pub labels: Vec<String>,
+}
+
+impl ListItemProvider for Test {
+    fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    fn detail(&self) -> Option<String> {
+        (!self.labels.is_empty()).then(|| self.labels.join(", "))
+    }
+}
+
+#[derive(Deserialize)]
+struct ShowOnly {
+    #[serde(default)]
+    tests: Vec<TestInfo>,
+}
+
+#[derive(Deserialize)]
+struct TestInfo {
+    name: String,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    value: serde_json::Value,
+}
+
+/// The arguments selecting the tests of `build_dir`, through the test preset
+/// linked to the selected configure preset when there is one.
+pub fn args(configure: &Configure, build_dir: &Path, filter: &Filter) -> Vec<String> {
+    let mut args = match configure
+        .preset
+        .as_ref()
+        .and_then(|p| p.test_preset.as_ref())
+    {
+        Some(test_preset) => vec!["--preset".to_string(), test_preset.clone()],
+        None => vec![
+            "--test-dir".to_string(),
+            build_dir.to_string_lossy().into_owned(),
+        ],
+    };
+    args.extend(filter.args());
+    args
+}
+
+/// Lists the tests known to CTest with `--show-only=json-v1`.
+pub async fn list(source_dir: &Path, build_dir: &Path, configure: &Configure) -> Result<Vec<Test>> {
+    let mut args = args(configure, build_dir, &Filter::All);
+    args.push("--show-only=json-v1".to_string());
+    let output = Command::new(&configure.commands.ctest)
+        .args(&args)
+        .current_dir(source_dir)
+        .envs(configure.env(&Default::default()))
+        .kill_on_drop(true)
+        .output()
+        .await
+        .wrap_err_with(|| format!("failed to run {}", configure.commands.ctest))?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "ctest failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let info: ShowOnly =
+        serde_json::from_slice(&output.stdout).wrap_err("failed to parse the ctest test list")?;
+
+    Ok(info
+        .tests
+        .into_iter()
+        .map(|test| Test {
+            labels: test
+                .properties
+                .iter()
+                .find(|p| p.name == "LABELS")
+                .and_then(|p| p.value.as_array())
+                .map(|labels| {
+                    labels
+                        .iter()
+                        .filter_map(|l| l.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            name: test.name,
+        })
+        .collect())
+}
+
+/// Test result counts, collected from CTest's streamed output.
+#[derive(Default, Clone, Debug)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub timeout: usize,
+    pub not_run: usize,
+}
+
+impl Summary {
+    /// Counts the result reported by a line of CTest output, if any.
+    pub fn line(&mut self, line: &str) {
+        let Some(status) = RESULT.captures(line).and_then(|c| c.get(1)) else {
+            return;
+        };
+        match status.as_str() {
+            "Passed" => self.passed += 1,
+            "Timeout" => self.timeout += 1,
+            "Not Run" | "Skipped" => self.not_run += 1,
+            _ => self.failed += 1,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![
+            format!("{} passed", self.passed),
+            format!("{} failed", self.failed),
+            format!("{} timeout", self.timeout),
+        ];
+        if self.not_run > 0 {
+            parts.push(format!("{} not run", self.not_run));
+        }
+        parts.join(" · ")
+    }
+}
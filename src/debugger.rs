@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use crate::{config::Commands, utils};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Gdb,
+    Lldb,
+}
+
+impl Kind {
+    /// Guesses the kind from the program name, e.g. `gdb-multiarch` or
+    /// `/usr/bin/lldb-18`.
+    fn from_program(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if name.contains("lldb") {
+            Kind::Lldb
+        } else {
+            Kind::Gdb
+        }
+    }
+
+    /// The arguments that start debugging `executable` with `args`.
+    fn args(&self, executable: &str, args: &[String]) -> Vec<String> {
+        let mut result = match self {
+            Kind::Gdb => vec!["--args".to_string()],
+            Kind::Lldb => vec!["--".to_string()],
+        };
+        result.push(executable.to_string());
+        result.extend(args.iter().cloned());
+        result
+    }
+}
+
+/// The debugger the Debug action runs, either in the output pane or in an
+/// external terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Debugger {
+    pub kind: Kind,
+    pub program: String,
+    /// The command opening a terminal that runs the rest of the command line,
+    /// e.g. `alacritty -e`. Empty to run in the output pane.
+    pub terminal: Vec<String>,
+}
+
+impl Debugger {
+    /// Finds the configured debugger, or else the first installed one of the
+    /// platform's preferred debuggers.
+    pub fn detect(commands: &Commands) -> Option<Self> {
+        let program = match &commands.debugger {
+            Some(program) => Some(program.clone()).filter(|p| is_installed(p))?,
+            None => preferred()
+                .into_iter()
+                .find(|p| utils::which(p).is_some())?
+                .to_string(),
+        };
+        let terminal = commands
+            .terminal
+            .as_deref()
+            .map(utils::split_args)
+            .unwrap_or_default();
+        Some(Self {
+            kind: Kind::from_program(&program),
+            program,
+            terminal,
+        })
+    }
+
+    /// The program and arguments to spawn to debug `executable` with `args`.
+    pub fn command(&self, executable: &Path, args: &[String]) -> (String, Vec<String>) {
+        let debugger_args = self.kind.args(&executable.to_string_lossy(), args);
+        match self.terminal.split_first() {
+            Some((terminal, terminal_args)) => {
+                let mut args = terminal_args.to_vec();
+                args.push(self.program.clone());
+                args.extend(debugger_args);
+                (terminal.clone(), args)
+            }
+            None => (self.program.clone(), debugger_args),
+        }
+    }
+}
+
+fn preferred() -> [&'static str; 2] {
+    if cfg!(target_os = "macos") {
+        ["lldb", "gdb"]
+    } else {
+        ["gdb", "lldb"]
+    }
+}
+
+fn is_installed(program: &str) -> bool {
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        Path::new(program).is_file()
+    } else {
+        utils::which(program).is_some()
+    }
+}
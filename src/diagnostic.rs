@@ -0,0 +1,236 @@
+use std::{path::PathBuf, sync::LazyLock};
+
+use ratatui::{
+    style::{Color, Stylize},
+    text::Span,
+};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::list_box::ListItemProvider;
+
+/// GCC and Clang, e.g. `src/main.cpp:12:5: error: expected ';'`.
+static GCC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?):(\d+):(?:(\d+):)?\s+(fatal error|error|warning|note):\s*(.*)$")
+        .expect("valid regex")
+});
+
+/// MSVC, e.g. `C:\src\main.cpp(12,5): error C2143: syntax error`.
+static MSVC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(.+?)\((\d+)(?:,(\d+))?\)\s*:\s+(fatal error|error|warning|note)(?:\s+([A-Z]+\d+))?\s*:\s*(.*)$",
+    )
+    .expect("valid regex")
+});
+
+/// The include chain GCC and Clang print before a diagnostic in a header,
+/// e.g. `In file included from src/main.cpp:3:` then `   from src/a.h:1,`.
+static INCLUDED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:In file included from|\s+from)\s+(.+?):(\d+)(?::\d+)?[:,]$")
+        .expect("valid regex")
+});
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Self {
+        match s {
+            "fatal error" | "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            _ => Severity::Note,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Error => Color::LightRed,
+            Severity::Warning => Color::LightYellow,
+            Severity::Note => Color::LightBlue,
+        }
+    }
+
+    pub fn icon(&self) -> Span<'static> {
+        match self {
+            Severity::Error => "✗".fg(self.color()),
+            Severity::Warning => "!".fg(self.color()),
+            Severity::Note => "·".fg(self.color()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The file as the compiler printed it, often relative to the build dir.
+    pub file: PathBuf,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub message: String,
+    /// The headers leading to `file`, outermost last.
+    pub included_from: Vec<(PathBuf, u32)>,
+    /// The output line it was parsed from.
+    pub output_line: usize,
+}
+
+impl Diagnostic {
+    pub fn location(&self) -> String {
+        match self.column {
+            Some(column) => format!("{}:{}:{}", self.file.display(), self.line, column),
+            None => format!("{}:{}", self.file.display(), self.line),
+        }
+    }
+}
+
+impl ListItemProvider for Diagnostic {
+    fn as_str(&self) -> &str {
+        &self.message
+    }
+
+    fn detail(&self) -> Option<String> {
+        let mut detail = self.location();
+        if let Some((file, line)) = self.included_from.first() {
+            detail.push_str(&format!(" (included from {}:{line})", file.display()));
+        }
+        Some(detail)
+    }
+
+    fn icon(&self) -> Option<Span<'static>> {
+        Some(self.severity.icon())
+    }
+}
+
+/// Collects diagnostics from compiler output, one line at a time.
+#[derive(Default, Debug)]
+pub struct Parser {
+    included_from: Vec<(PathBuf, u32)>,
+}
+
+impl Parser {
+    /// Parses the line at `output_line`, returning the diagnostic it starts.
+    pub fn line(&mut self, line: &str, output_line: usize) -> Option<Diagnostic> {
+        let line = line.trim_end();
+        if let Some(c) = INCLUDED.captures(line) {
+            if line.starts_with("In file") {
+                self.included_from.clear();
+            }
+            self.included_from
+                .push((PathBuf::from(&c[1]), c[2].parse().ok()?));
+            return None;
+        }
+        let (c, message) = if let Some(c) = GCC.captures(line) {
+            let message = c[5].to_string();
+            (c, message)
+        } else if let Some(c) = MSVC.captures(line) {
+            let message = match c.get(5) {
+                Some(code) => format!("{}: {}", code.as_str(), &c[6]),
+                None => c[6].to_string(),
+            };
+            (c, message)
+        } else {
+            // The chain only leads to the diagnostic right after it.
+            self.included_from.clear();
+            return None;
+        };
+        Some(Diagnostic {
+            severity: Severity::parse(&c[4]),
+            file: PathBuf::from(&c[1]),
+            line: c[2].parse().ok()?,
+            column: c.get(3).and_then(|m| m.as_str().parse().ok()),
+            message,
+            included_from: std::mem::take(&mut self.included_from),
+            output_line,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Vec<Diagnostic> {
+        let mut parser = Parser::default();
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| parser.line(line, i))
+            .collect()
+    }
+
+    #[test]
+    fn parses_gcc_and_clang() {
+        let diagnostics = parse(&[
+            "src/main.cpp:12:5: error: expected ';' before '}' token",
+            "src/util.h:3: warning: unused variable 'x'",
+            "/abs/path.c:1:1: fatal error: foo.h: No such file or directory",
+        ]);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].location(), "src/main.cpp:12:5");
+        assert_eq!(diagnostics[0].message, "expected ';' before '}' token");
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].column, None);
+        assert_eq!(diagnostics[2].severity, Severity::Error);
+        assert_eq!(diagnostics[2].message, "foo.h: No such file or directory");
+        assert_eq!(diagnostics[2].output_line, 2);
+    }
+
+    #[test]
+    fn parses_msvc() {
+        let diagnostics = parse(&[
+            r"C:\src\main.cpp(12,5): error C2143: syntax error: missing ';'",
+            r"  C:\src\a.h(7): warning C4101: 'x': unreferenced local variable",
+        ]);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, PathBuf::from(r"C:\src\main.cpp"));
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (12, Some(5)));
+        assert_eq!(diagnostics[0].message, "C2143: syntax error: missing ';'");
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].column, None);
+    }
+
+    #[test]
+    fn follows_include_chains() {
+        let diagnostics = parse(&[
+            "In file included from src/a.h:2,",
+            "                 from src/main.cpp:3:",
+            "src/b.h:4:1: error: 'foo' does not name a type",
+            "src/b.h:5:1: error: 'bar' does not name a type",
+        ]);
+        assert_eq!(
+            diagnostics[0].included_from,
+            [
+                (PathBuf::from("src/a.h"), 2),
+                (PathBuf::from("src/main.cpp"), 3),
+            ]
+        );
+        assert!(diagnostics[1].included_from.is_empty());
+    }
+
+    #[test]
+    fn forgets_include_chains_on_other_lines() {
+        let diagnostics = parse(&[
+            "In file included from src/main.cpp:3:",
+            "[ 50%] Building CXX object CMakeFiles/app.dir/main.cpp.o",
+            "src/b.h:4:1: error: 'foo' does not name a type",
+        ]);
+        assert!(diagnostics[0].included_from.is_empty());
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        let diagnostics = parse(&[
+            "[ 50%] Building CXX object CMakeFiles/app.dir/main.cpp.o",
+            "make[2]: *** [CMakeFiles/app.dir/build.make:76: main.o] Error 1",
+            "error: linker command failed",
+            "12:30:45 warning: not a file",
+            "-- Configuring done (0.1s)",
+        ]);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use eyre::{Result, WrapErr, eyre};
+
+use crate::{config::Commands, utils};
+
+/// A place in a file to open.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    fn with_line(&self) -> String {
+        format!("{}:{}", self.file.display(), self.line)
+    }
+
+    fn with_column(&self) -> String {
+        format!("{}:{}", self.with_line(), self.column.unwrap_or(1))
+    }
+}
+
+/// How an editor takes the line to jump to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    /// `+line file`: vi, vim, nvim and most others.
+    Vi,
+    /// `+line:column file`.
+    Emacs,
+    /// `-g file:line:column`.
+    Code,
+    /// `file:line:column`.
+    Helix,
+}
+
+impl Kind {
+    fn from_program(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .map(|n| n.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "emacs" | "emacsclient" => Kind::Emacs,
+            "code" | "code-insiders" | "codium" | "cursor" => Kind::Code,
+            "hx" | "helix" => Kind::Helix,
+            _ => Kind::Vi,
+        }
+    }
+
+    fn args(&self, location: &Location) -> Vec<String> {
+        let file = location.file.to_string_lossy().into_owned();
+        match self {
+            Kind::Vi => vec![format!("+{}", location.line), file],
+            Kind::Emacs => vec![
+                format!("+{}:{}", location.line, location.column.unwrap_or(1)),
+                file,
+            ],
+            Kind::Code => vec!["-g".to_string(), location.with_column()],
+            Kind::Helix => vec![location.with_column()],
+        }
+    }
+}
+
+/// The editor command line: `commands.editor`, `$VISUAL` or `$EDITOR`,
+/// falling back to `vi`.
+fn editor(commands: &Commands) -> Vec<String> {
+    commands
+        .editor
+        .clone()
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .map(|e| utils::split_args(&e))
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+/// The program and arguments opening `location`.
+pub fn command(commands: &Commands, location: &Location) -> (String, Vec<String>) {
+    let mut editor = editor(commands);
+    let program = editor.remove(0);
+    editor.extend(Kind::from_program(&program).args(location));
+    (program, editor)
+}
+
+/// Runs the editor on `location` in the current terminal until it exits.
+pub fn open(commands: &Commands, location: &Location) -> Result<()> {
+    let (program, args) = command(commands, location);
+    let status = Command::new(&program)
+        .args(&args)
+        .status()
+        .wrap_err_with(|| format!("failed to run {program}"))?;
+    if !status.success() {
+        return Err(eyre!("{program} exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Resolves a path printed by a compiler, relative to the directory the
+/// build ran in or to the source directory.
+pub fn resolve(file: &Path, build_dir: &Path, source_dir: &Path) -> PathBuf {
+    if file.is_absolute() {
+        return file.to_path_buf();
+    }
+    [build_dir, source_dir]
+        .iter()
+        .map(|dir| dir.join(file))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| source_dir.join(file))
+}
@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::project::{Project, TargetKind};
+
+const API_DIR: &str = ".cmake/api/v1";
+
+/// Utility targets that IDE generators add to every project.
+const GENERATED_TARGETS: [&str; 4] = ["ALL_BUILD", "ZERO_CHECK", "INSTALL", "RUN_TESTS"];
+
+#[derive(Deserialize)]
+struct Index {
+    objects: Vec<ObjectRef>,
+}
+
+#[derive(Deserialize)]
+struct ObjectRef {
+    kind: String,
+    #[serde(rename = "jsonFile")]
+    json_file: String,
+}
+
+#[derive(Deserialize)]
+struct Codemodel {
+    paths: Paths,
+    configurations: Vec<Configuration>,
+}
+
+#[derive(Deserialize)]
+struct Paths {
+    source: PathBuf,
+    build: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    name: String,
+    targets: Vec<TargetRef>,
+}
+
+#[derive(Deserialize)]
+struct TargetRef {
+    #[serde(rename = "jsonFile")]
+    json_file: String,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    paths: Paths,
+    #[serde(default)]
+    artifacts: Vec<PathEntry>,
+    backtrace: Option<usize>,
+    #[serde(rename = "backtraceGraph")]
+    backtrace_graph: Option<BacktraceGraph>,
+}
+
+#[derive(Deserialize)]
+struct PathEntry {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct BacktraceGraph {
+    nodes: Vec<BacktraceNode>,
+    files: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct BacktraceNode {
+    file: usize,
+    line: Option<usize>,
+}
+
+/// Asks CMake to write a codemodel reply on the next configure of `build_dir`.
+pub fn write_query(build_dir: &Path) -> Result<()> {
+    let query = build_dir.join(API_DIR).join("query");
+    fs::create_dir_all(&query)?;
+    let file = query.join("codemodel-v2");
+    if !file.exists() {
+        fs::write(file, "")?;
+    }
+    Ok(())
+}
+
+/// Reads the targets of a configured build tree from the CMake File API,
+/// with the artifacts of `configuration` when a multi-config generator
+/// wrote several.
+///
+/// Returns `None` when `build_dir` has not been configured yet or CMake has
+/// not written a codemodel reply, in which case the caller should fall back
+/// to [`crate::parser::refresh_list`].
+pub fn refresh_list(build_dir: &Path, configuration: &str) -> Result<Option<Vec<Project>>> {
+    if !build_dir.join("CMakeCache.txt").is_file() {
+        return Ok(None);
+    }
+    write_query(build_dir)?;
+
+    let reply = build_dir.join(API_DIR).join("reply");
+    let Some(index) = latest_index(&reply)? else {
+        return Ok(None);
+    };
+    let index: Index = read_json(&index)?;
+    let Some(codemodel) = index.objects.iter().find(|o| o.kind == "codemodel") else {
+        return Ok(None);
+    };
+    let codemodel: Codemodel = read_json(&reply.join(&codemodel.json_file))?;
+    let configurations = &codemodel.configurations;
+    let Some(configuration) = configurations
+        .iter()
+        .find(|c| c.name == configuration)
+        .or(configurations.first())
+    else {
+        return Ok(None);
+    };
+
+    let mut projects = Vec::new();
+    for target in &configuration.targets {
+        let target: Target = read_json(&reply.join(&target.json_file))?;
+        let Some(kind) = TargetKind::from_file_api(&target.kind) else {
+            continue;
+        };
+        if GENERATED_TARGETS.contains(&target.name.as_str()) {
+            continue;
+        }
+        projects.push(project(target, kind, &codemodel.paths));
+    }
+    projects.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(Some(projects))
+}
+
+fn project(target: Target, kind: TargetKind, paths: &Paths) -> Project {
+    let dir = match target.paths.source.as_path() {
+        p if p == Path::new(".") => PathBuf::new(),
+        p => p.to_path_buf(),
+    };
+    let (source, line) = target
+        .backtrace
+        .zip(target.backtrace_graph.as_ref())
+        .and_then(|(i, graph)| {
+            let node = graph.nodes.get(i)?;
+            Some((graph.files.get(node.file)?.clone(), node.line?))
+        })
+        .unwrap_or_else(|| (dir.join("CMakeLists.txt"), 0));
+    let source = source
+        .strip_prefix(&paths.source)
+        .map(Path::to_path_buf)
+        .unwrap_or(source);
+
+    let artifact = target.artifacts.first().map(|a| paths.build.join(&a.path));
+
+    Project {
+        target: target.name,
+        kind,
+        source,
+        dir,
+        line,
+        artifact,
+        ..Default::default()
+    }
+}
+
+/// Index files are named `index-<timestamp>.json`; the latest sorts last.
+fn latest_index(reply: &Path) -> Result<Option<PathBuf>> {
+    if !reply.is_dir() {
+        return Ok(None);
+    }
+    let mut indices: Vec<PathBuf> = fs::read_dir(reply)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("index-") && n.ends_with(".json"))
+        })
+        .collect();
+    indices.sort();
+    Ok(indices.pop())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = fs::read(path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&file).wrap_err_with(|| format!("failed to parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Ninja Multi-Config build tree of a project with an executable at
+    /// the top and a static library in `lib`, defined through a helper
+    /// function.
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/file_api")
+    }
+
+    fn projects() -> Vec<Project> {
+        refresh_list(&fixture(), "Debug").unwrap().unwrap()
+    }
+
+    #[test]
+    fn skips_generated_targets() {
+        let targets: Vec<_> = projects().into_iter().map(|p| p.target).collect();
+        assert_eq!(targets, ["app", "util"]);
+    }
+
+    #[test]
+    fn reads_artifacts() {
+        let projects = projects();
+        let app = &projects[0];
+        assert_eq!(app.kind, TargetKind::Executable);
+        assert_eq!(
+            app.artifact.as_deref(),
+            Some(Path::new("/home/dev/demo/build/bin/app"))
+        );
+        assert_eq!(projects[1].kind, TargetKind::StaticLibrary);
+        assert_eq!(projects[1].dir, Path::new("lib"));
+    }
+
+    #[test]
+    fn locates_definitions_from_backtraces() {
+        let projects = projects();
+        assert_eq!(projects[0].source, Path::new("CMakeLists.txt"));
+        assert_eq!(projects[0].line, 12);
+        // Absolute paths inside the source directory become relative.
+        assert_eq!(projects[1].source, Path::new("cmake/Helpers.cmake"));
+        assert_eq!(projects[1].line, 7);
+    }
+
+    #[test]
+    fn reads_the_selected_configuration() {
+        let artifact = |configuration| {
+            let projects = refresh_list(&fixture(), configuration).unwrap().unwrap();
+            projects[0].artifact.clone().unwrap()
+        };
+        assert_eq!(
+            artifact("Release"),
+            Path::new("/home/dev/demo/build/bin/Release/app")
+        );
+        // Single-config trees have one, whatever the build type.
+        assert_eq!(
+            artifact("MinSizeRel"),
+            Path::new("/home/dev/demo/build/bin/app")
+        );
+    }
+
+    #[test]
+    fn needs_a_configured_tree() {
+        let dir = fixture().join(API_DIR);
+        assert!(refresh_list(&dir, "Debug").unwrap().is_none());
+    }
+}
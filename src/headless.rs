@@ -0,0 +1,283 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use eyre::{Result, eyre};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::{
+    action::{Action, Console},
+    build_dir,
+    config::Config,
+    configure::Configure,
+    debugger::Debugger,
+    diagnostic::{self, Diagnostic},
+    job::{Chunk, Control, Output, Signal},
+    project::{self, Project},
+    state::State,
+};
+
+/// How the results are printed.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The output as it is.
+    #[default]
+    Text,
+    /// A JSON array of the targets, or a JSON object per line for each event
+    /// of a job.
+    Json,
+}
+
+/// A target as listed in JSON.
+#[derive(Serialize)]
+struct Target<'a> {
+    target: &'a str,
+    /// The built binary, when it exists or is known.
+    artifact: Option<PathBuf>,
+    kind: &'static str,
+    source: &'a Path,
+    line: usize,
+}
+
+/// What happens in a job, printed as a JSON line.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Start {
+        action: String,
+        target: Option<&'a str>,
+    },
+    Output {
+        line: usize,
+        text: String,
+    },
+    Diagnostic(&'a Diagnostic),
+    Error {
+        message: String,
+    },
+    Exit {
+        code: i32,
+        success: bool,
+        duration_ms: u128,
+    },
+}
+
+impl Event<'_> {
+    fn print(&self) -> Result<()> {
+        writeln!(io::stdout(), "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Runs actions without the terminal interface, printing their output to
+/// stdout, e.g. in CI or git hooks.
+pub struct Headless {
+    /// The top-level `CMakeLists.txt`.
+    path: PathBuf,
+    build_dir: PathBuf,
+    configure: Configure,
+    state: State,
+    format: Format,
+}
+
+impl Headless {
+    pub fn new(
+        path: PathBuf,
+        build_dir: Option<PathBuf>,
+        mut configure: Configure,
+        config: &Config,
+        format: Format,
+    ) -> Self {
+        let source_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let state = State::load(&source_dir).unwrap_or_else(|e| {
+            eprintln!("{e:#}");
+            State::default()
+        });
+        let build_dir = build_dir::initial(&source_dir, build_dir.or(state.build_dir.clone()));
+        configure.commands = config.commands.clone();
+        configure.debugger = Debugger::detect(&config.commands);
+        Self {
+            path,
+            build_dir,
+            configure,
+            state,
+            format,
+        }
+    }
+
+    pub fn projects(&self) -> Result<Vec<Project>> {
+        project::discover(
+            &self.path,
+            &self.build_dir,
+            self.configure.configuration(),
+            &self.state.targets,
+        )
+    }
+
+    /// The target called `target`, checking that `action` applies to it.
+    pub fn project(&self, target: &str, action: &Action) -> Result<Project> {
+        let project = self
+            .projects()?
+            .into_iter()
+            .find(|p| p.target == target)
+            .ok_or_else(|| eyre!("No target named `{target}`"))?;
+        if !Action::available_for(project.kind).contains(action) {
+            return Err(eyre!(
+                "Cannot {} `{target}`, a {} target",
+                action.to_str().to_lowercase(),
+                project.kind.to_str()
+            ));
+        }
+        Ok(project)
+    }
+
+    /// Prints the targets, one per line or as a JSON array.
+    pub fn list(&self) -> Result<()> {
+        let projects = self.projects()?;
+        match self.format {
+            Format::Text => {
+                for project in projects {
+                    writeln!(
+                        io::stdout(),
+                        "{}\t{}\t{}:{}",
+                        project.target,
+                        project.kind.to_str(),
+                        project.source.display(),
+                        project.line
+                    )?;
+                }
+            }
+            Format::Json => {
+                let targets: Vec<_> = projects
+                    .iter()
+                    .map(|project| Target {
+                        target: &project.target,
+                        artifact: project
+                            .resolve_artifact(&self.build_dir)
+                            .ok()
+                            .or_else(|| project.artifact.clone()),
+                        kind: project.kind.to_str(),
+                        source: &project.source,
+                        line: project.line,
+                    })
+                    .collect();
+                writeln!(io::stdout(), "{}", serde_json::to_string(&targets)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `action` on `project`, returning the exit code of the last
+    /// process it ran. Ctrl+C stops it like in the interface.
+    ///
+    /// In text, a program that is run gets the standard streams, and the
+    /// output of the steps before it goes to stderr.
+    pub async fn run(&self, action: Action, project: Project) -> Result<i32> {
+        let (console, to_stderr) = match self.format {
+            Format::Text => (Console::Inherited, action == Action::Run),
+            Format::Json => (Console::Piped, false),
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let control = Control::default();
+        let out = Output::new(0, tx, control.clone());
+        if self.format == Format::Json {
+            Event::Start {
+                action: action.to_str().to_lowercase(),
+                target: (!project.target.is_empty()).then_some(project.target.as_str()),
+            }
+            .print()?;
+        }
+        let started = Instant::now();
+        let (path, build_dir, configure) = (
+            self.path.clone(),
+            self.build_dir.clone(),
+            self.configure.clone(),
+        );
+        let task = tokio::spawn(async move {
+            if let Err(e) = action
+                .run(&out, console, &project, &path, &build_dir, &configure)
+                .await
+            {
+                out.control.set_failed();
+                out.send(Err(e)).await.ok();
+            }
+        });
+
+        let mut parser = diagnostic::Parser::default();
+        let mut line = 0;
+        let mut stops = 0;
+        loop {
+            tokio::select! {
+                chunk = rx.recv() => match chunk {
+                    Some((_, Ok(Chunk::Text(text)))) => {
+                        for text in text.lines.iter().map(ToString::to_string) {
+                            self.print_line(&mut parser, line, text, to_stderr)?;
+                            line += 1;
+                        }
+                    }
+                    Some((_, Ok(Chunk::Partial(_)))) => {}
+                    Some((_, Err(e))) => self.print_error(format!("{e:#}"))?,
+                    None => break,
+                },
+                Ok(()) = tokio::signal::ctrl_c() => {
+                    control.signal(if stops == 0 { Signal::Interrupt } else { Signal::Kill });
+                    stops += 1;
+                }
+            }
+        }
+        task.await?;
+
+        let code = match control.exit_code() {
+            Some(0) if control.is_failed() => 1,
+            Some(code) => code,
+            None => 1,
+        };
+        if self.format == Format::Json {
+            Event::Exit {
+                code,
+                success: code == 0,
+                duration_ms: started.elapsed().as_millis(),
+            }
+            .print()?;
+        }
+        Ok(code)
+    }
+
+    fn print_error(&self, message: String) -> Result<()> {
+        // A failed step ends with an empty error, its output says why.
+        if message.is_empty() {
+            return Ok(());
+        }
+        match self.format {
+            Format::Text => writeln!(io::stderr(), "{message}")?,
+            Format::Json => Event::Error { message }.print()?,
+        }
+        Ok(())
+    }
+
+    /// Prints line number `line` of the output, followed in JSON by the
+    /// diagnostic it holds. Fails once stdout is closed, e.g. by `head`.
+    fn print_line(
+        &self,
+        parser: &mut diagnostic::Parser,
+        line: usize,
+        text: String,
+        to_stderr: bool,
+    ) -> Result<()> {
+        match self.format {
+            Format::Text if to_stderr => writeln!(io::stderr(), "{text}")?,
+            Format::Text => writeln!(io::stdout(), "{text}")?,
+            Format::Json => {
+                let diagnostic = parser.line(&text, line);
+                Event::Output { line, text }.print()?;
+                if let Some(diagnostic) = &diagnostic {
+                    Event::Diagnostic(diagnostic).print()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use eyre::Result;
+use ratatui::{
+    style::{Color, Stylize},
+    text::{Line, Span, Text},
+    widgets::ListState,
+};
+use tokio::{
+    sync::mpsc::{self, error::SendError},
+    task::JoinHandle,
+};
+
+use crate::{
+    action::Action,
+    config::theme::Theme,
+    ctest,
+    diagnostic::{self, Diagnostic, Severity},
+    line_buffer::LineBuffer,
+    list_box::{ListItemProvider, state::ListBoxState},
+    log::Tee,
+    project::Project,
+    pty::Terminal,
+    stateful_list::StatefulList,
+    utils,
+};
+
+/// The signals sent to stop a job, in the order they escalate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+impl Signal {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Signal::Interrupt => "SIGINT",
+            Signal::Terminate => "SIGTERM",
+            Signal::Kill => "SIGKILL",
+        }
+    }
+
+    #[cfg(unix)]
+    fn number(&self) -> libc::c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+        }
+    }
+}
+
+/// Shared between a job and the app: the process the job currently waits
+/// for, whether it was asked to stop, and how it ended.
+#[derive(Clone, Default, Debug)]
+pub struct Control {
+    pid: Arc<Mutex<Option<u32>>>,
+    exit_code: Arc<Mutex<Option<i32>>>,
+    cancelled: Arc<AtomicBool>,
+    failed: Arc<AtomicBool>,
+}
+
+impl Control {
+    pub fn set_child(&self, pid: Option<u32>) {
+        *self.pid.lock().unwrap_or_else(|e| e.into_inner()) = pid;
+    }
+
+    /// Records the exit code of the last process the job ran.
+    pub fn set_exit_code(&self, code: Option<i32>) {
+        *self.exit_code.lock().unwrap_or_else(|e| e.into_inner()) = code;
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Marks the job as failed although its processes succeeded, e.g. when
+    /// the executable to run was not found.
+    pub fn set_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Whether the job was stopped, so that it does not start its next step.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Sends `signal` to the child's process group, which every child leads,
+    /// so that e.g. the compilers of a build are stopped too.
+    pub fn signal(&self, signal: Signal) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        let Some(pid) = *self.pid.lock().unwrap_or_else(|e| e.into_inner()) else {
+            return;
+        };
+        #[cfg(unix)]
+        {
+            let pid = pid as libc::pid_t;
+            // SAFETY: kill has no memory safety requirements.
+            unsafe {
+                if libc::kill(-pid, signal.number()) != 0 {
+                    libc::kill(pid, signal.number());
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (pid, signal);
+    }
+}
+
+/// Where a job sends its output.
+#[derive(Clone, Debug)]
+pub struct Output {
+    id: usize,
+    tx: mpsc::Sender<(usize, Result<Chunk>)>,
+    pub control: Control,
+    /// The color of what processes print to stderr.
+    pub stderr: Color,
+}
+
+impl Output {
+    pub fn new(id: usize, tx: mpsc::Sender<(usize, Result<Chunk>)>, control: Control) -> Self {
+        Self {
+            id,
+            tx,
+            control,
+            stderr: Theme::default().stderr,
+        }
+    }
+
+    pub async fn send(
+        &self,
+        text: Result<Text<'static>>,
+    ) -> Result<(), SendError<(usize, Result<Chunk>)>> {
+        self.tx.send((self.id, text.map(Chunk::Text))).await
+    }
+
+    pub async fn send_partial(
+        &self,
+        line: Line<'static>,
+    ) -> Result<(), SendError<(usize, Result<Chunk>)>> {
+        self.tx.send((self.id, Ok(Chunk::Partial(line)))).await
+    }
+}
+
+/// What a job writes to its output.
+#[derive(Debug)]
+pub enum Chunk {
+    /// Complete lines.
+    Text(Text<'static>),
+    /// The line being written, shown until the next chunk.
+    Partial(Line<'static>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Succeeded,
+    Failed(Option<i32>),
+    Stopped,
+}
+
+impl Status {
+    pub fn icon(&self) -> Span<'static> {
+        match self {
+            Status::Running => "●".light_yellow(),
+            Status::Succeeded => "✓".light_green(),
+            Status::Failed(_) => "✗".light_red(),
+            Status::Stopped => "■".gray(),
+        }
+    }
+}
+
+/// An action or configure step with its output.
+pub struct Job {
+    pub id: usize,
+    pub title: String,
+    /// What was launched, `None` for a configure step.
+    pub launch: Option<(Action, Project)>,
+    handle: JoinHandle<()>,
+    control: Control,
+    /// How many times the job was asked to stop.
+    stops: usize,
+    pub started: SystemTime,
+    started_at: Instant,
+    finished: Option<Duration>,
+    pub output: LineBuffer,
+    /// The pseudo-terminal of the program it runs, if any.
+    pub terminal: Option<Terminal>,
+    pub test_summary: Option<ctest::Summary>,
+    /// The compiler diagnostics found in the output.
+    pub problems: ListBoxState<Diagnostic>,
+    parser: diagnostic::Parser,
+    /// Where the output is copied to, if teeing.
+    pub log: Option<Tee>,
+}
+
+impl Job {
+    pub fn new(
+        id: usize,
+        title: String,
+        launch: Option<(Action, Project)>,
+        handle: JoinHandle<()>,
+        control: Control,
+        scrollback: usize,
+    ) -> Self {
+        let test_summary = launch
+            .as_ref()
+            .is_some_and(|(action, _)| matches!(action, Action::Test(_)))
+            .then(ctest::Summary::default);
+        Self {
+            id,
+            title,
+            launch,
+            handle,
+            control,
+            stops: 0,
+            started: SystemTime::now(),
+            started_at: Instant::now(),
+            finished: None,
+            output: LineBuffer::new(scrollback),
+            terminal: None,
+            test_summary,
+            problems: ListBoxState {
+                list: StatefulList {
+                    state: ListState::default(),
+                    items: vec![],
+                },
+                active: false,
+                filter: None,
+            },
+            parser: diagnostic::Parser::default(),
+            log: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+
+    pub fn status(&self) -> Status {
+        if self.is_running() {
+            Status::Running
+        } else if self.control.is_cancelled() {
+            Status::Stopped
+        } else {
+            match self.control.exit_code() {
+                Some(0) if !self.control.is_failed() => Status::Succeeded,
+                code => Status::Failed(code),
+            }
+        }
+    }
+
+    /// How long the job ran, or has been running.
+    pub fn duration(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started_at.elapsed())
+    }
+
+    /// Records when the job ended, to be called periodically.
+    pub fn update(&mut self) {
+        if self.finished.is_none() && !self.is_running() {
+            self.finished = Some(self.started_at.elapsed());
+            if let Some(log) = &mut self.log
+                && let Err(e) = log.flush()
+            {
+                self.log = None;
+                self.output
+                    .push_text(Text::raw(format!("failed to write the log: {e}")).light_red());
+            }
+        }
+    }
+
+    pub fn push(&mut self, chunk: Result<Chunk>) {
+        match chunk {
+            Ok(Chunk::Text(text)) => {
+                for line in text.lines {
+                    let plain = line.to_string();
+                    if let Some(summary) = &mut self.test_summary {
+                        summary.line(&plain);
+                    }
+                    if let Some(diagnostic) = self.parser.line(&plain, self.output.end()) {
+                        self.problems.list.items.push(diagnostic);
+                    }
+                    self.push_line(line.patch_style(text.style));
+                }
+            }
+            Ok(Chunk::Partial(line)) => self.output.set_partial(Some(line)),
+            Err(e) => {
+                let message = format!("{e:#}");
+                if message.is_empty() {
+                    return;
+                }
+                let text = Text::raw(message).light_red();
+                let style = text.style;
+                for line in text.lines {
+                    self.push_line(line.patch_style(style));
+                }
+            }
+        }
+    }
+
+    /// Appends a complete line, copying it to the log.
+    fn push_line(&mut self, line: Line<'static>) {
+        if let Some(log) = &mut self.log
+            && let Err(e) = log.write(&line)
+        {
+            self.log = None;
+            self.output
+                .push_text(Text::raw(format!("failed to write the log: {e}")).light_red());
+        }
+        self.output.push(line);
+    }
+
+    /// The number of errors and warnings found in the output.
+    pub fn problem_counts(&self) -> (usize, usize) {
+        let count = |severity| {
+            self.problems
+                .list
+                .items
+                .iter()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        (count(Severity::Error), count(Severity::Warning))
+    }
+
+    /// Stops the job, escalating from SIGINT to SIGTERM to SIGKILL each time
+    /// it is called. Returns the signal sent.
+    pub fn stop(&mut self) -> Signal {
+        let signal = match self.stops {
+            0 => Signal::Interrupt,
+            1 => Signal::Terminate,
+            _ => Signal::Kill,
+        };
+        self.stops += 1;
+        // Even SIGKILL lets the task see the process exit, so that it still
+        // moves the screen to the output and reports the status.
+        self.control.signal(signal);
+        signal
+    }
+}
+
+impl ListItemProvider for Job {
+    fn as_str(&self) -> &str {
+        &self.title
+    }
+
+    fn detail(&self) -> Option<String> {
+        let mut detail = format!(
+            "started {} · {}",
+            utils::clock(self.started),
+            utils::format_duration(self.duration())
+        );
+        if let Status::Failed(Some(code)) = self.status() {
+            detail.push_str(&format!(" · exit code {code}"));
+        }
+        let (errors, warnings) = self.problem_counts();
+        if errors + warnings > 0 {
+            detail.push_str(&format!(" · {}", describe_counts(errors, warnings)));
+        }
+        Some(detail)
+    }
+
+    fn icon(&self) -> Option<Span<'static>> {
+        Some(self.status().icon())
+    }
+}
+
+/// E.g. `2 errors, 1 warning`.
+pub fn describe_counts(errors: usize, warnings: usize) -> String {
+    let plural = |n: usize, word: &str| match n {
+        1 => format!("1 {word}"),
+        n => format!("{n} {word}s"),
+    };
+    format!(
+        "{}, {}",
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    )
+}
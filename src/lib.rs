@@ -1,9 +1,28 @@
 use ratatui::{prelude::*, widgets::*};
 
 pub mod action;
+pub mod ansi;
 pub mod app;
+pub mod build_dir;
+pub mod config;
+pub mod configure;
+pub mod ctest;
+pub mod debugger;
+pub mod diagnostic;
+pub mod editor;
+pub mod file_api;
+pub mod headless;
+pub mod job;
+pub mod line_buffer;
 pub mod list_box;
+pub mod log;
 pub mod parser;
+pub mod presets;
 pub mod project;
+pub mod pty;
+pub mod run_editor;
+pub mod search;
+pub mod state;
 pub mod stateful_list;
+pub mod text_input;
 pub mod utils;
@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ratatui::text::{Line, Text};
+
+/// The default number of lines kept.
+pub const SCROLLBACK: usize = 10_000;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Output stored as lines, plus the line still being written. The oldest
+/// lines are dropped past the scrollback limit.
+#[derive(Debug)]
+pub struct LineBuffer {
+    lines: VecDeque<Line<'static>>,
+    partial: Option<Line<'static>>,
+    limit: usize,
+    /// How many lines were dropped, so that line numbers stay stable.
+    dropped: usize,
+    /// The position among the kept lines.
+    scroll: Scroll,
+    id: usize,
+    /// Counts the changes to the lines.
+    revision: usize,
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        Self::new(SCROLLBACK)
+    }
+}
+
+impl LineBuffer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            partial: None,
+            limit: limit.max(1),
+            dropped: 0,
+            scroll: Scroll::default(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            revision: 0,
+        }
+    }
+
+    /// Appends a complete line, replacing the partial one.
+    pub fn push(&mut self, line: Line<'static>) {
+        self.revision += 1;
+        self.partial = None;
+        self.lines.push_back(line);
+        if self.lines.len() > self.limit {
+            self.lines.pop_front();
+            self.dropped += 1;
+            self.scroll.offset = self.scroll.offset.saturating_sub(1);
+        }
+    }
+
+    /// Appends the lines of `text`, styled with its style.
+    pub fn push_text(&mut self, text: Text<'static>) {
+        let style = text.style;
+        for line in text.lines {
+            self.push(line.patch_style(style));
+        }
+    }
+
+    /// Shows `line` as the line being written until the next one.
+    pub fn set_partial(&mut self, line: Option<Line<'static>>) {
+        self.revision += 1;
+        self.partial = line;
+    }
+
+    /// Identifies the buffer and the state of its lines, so that what is
+    /// computed from them can be reused until a line is pushed.
+    pub fn revision(&self) -> (usize, usize) {
+        (self.id, self.revision)
+    }
+
+    /// The number of the next complete line, counting the dropped ones.
+    pub fn end(&self) -> usize {
+        self.dropped + self.lines.len()
+    }
+
+    /// The number of the first kept line.
+    pub fn first(&self) -> usize {
+        self.dropped
+    }
+
+    /// The number of lines to show, the partial one included.
+    pub fn height(&self) -> usize {
+        self.lines.len() + usize::from(self.partial.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.height() == 0
+    }
+
+    /// The kept lines, followed by the partial one.
+    pub fn iter(&self) -> impl Iterator<Item = &Line<'static>> {
+        self.lines.iter().chain(&self.partial)
+    }
+
+    /// The lines with their numbers.
+    pub fn numbered(&self) -> impl Iterator<Item = (usize, &Line<'static>)> {
+        (self.dropped..).zip(self.iter())
+    }
+
+    /// The line with number `number`, if still kept.
+    pub fn get(&self, number: usize) -> Option<&Line<'static>> {
+        let index = number.checked_sub(self.dropped)?;
+        self.lines
+            .get(index)
+            .or(self.partial.as_ref().filter(|_| index == self.lines.len()))
+    }
+
+    /// The first line shown in a view of `rows` lines.
+    pub fn offset(&self, rows: usize) -> usize {
+        self.scroll.offset(self.height(), rows)
+    }
+
+    /// Whether the view sticks to the last line.
+    pub fn is_following(&self) -> bool {
+        self.scroll.follow
+    }
+
+    pub fn scroll_mut(&mut self) -> &mut Scroll {
+        &mut self.scroll
+    }
+
+    /// Scrolls so that line number `line` is the first shown.
+    pub fn scroll_to(&mut self, line: usize) {
+        self.scroll.to(line.saturating_sub(self.dropped));
+    }
+}
+
+/// A scroll position in a list of lines that may keep growing.
+#[derive(Clone, Copy, Debug)]
+pub struct Scroll {
+    /// The first line shown.
+    pub offset: usize,
+    /// Whether the view sticks to the last line as lines come in.
+    pub follow: bool,
+}
+
+impl Default for Scroll {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            follow: true,
+        }
+    }
+}
+
+impl Scroll {
+    /// The first line shown of `height` lines in a view of `rows` lines.
+    pub fn offset(&self, height: usize, rows: usize) -> usize {
+        let max = height.saturating_sub(rows);
+        if self.follow {
+            max
+        } else {
+            self.offset.min(max)
+        }
+    }
+
+    pub fn up(&mut self, lines: usize, height: usize, rows: usize) {
+        self.offset = self.offset(height, rows).saturating_sub(lines);
+        self.follow = false;
+    }
+
+    /// Scrolls down, following the lines again once at the end.
+    pub fn down(&mut self, lines: usize, height: usize, rows: usize) {
+        let max = height.saturating_sub(rows);
+        self.offset = (self.offset(height, rows) + lines).min(max);
+        self.follow = self.offset == max;
+    }
+
+    pub fn to(&mut self, offset: usize) {
+        self.offset = offset;
+        self.follow = false;
+    }
+}
@@ -1,25 +1,76 @@
 use std::marker::PhantomData;
 
 use super::*;
+use crate::config::theme::Theme;
+pub mod fuzzy;
 pub mod state;
 use state::ListBoxState;
 
 pub trait ListItemProvider {
     fn as_str(&self) -> &str;
+
+    /// Extra information rendered dimmed after the item.
+    fn detail(&self) -> Option<String> {
+        None
+    }
+
+    /// A symbol rendered before the item, e.g. a status.
+    fn icon(&self) -> Option<Span<'static>> {
+        None
+    }
 }
 
 pub struct ListBox<T> {
-    title: &'static str,
+    title: String,
+    theme: Theme,
+    disabled: Box<dyn Fn(&T) -> bool>,
     phantom: PhantomData<T>,
 }
 
 impl<T> ListBox<T> {
-    pub fn new(title: &'static str) -> ListBox<T> {
+    pub fn new(title: impl Into<String>) -> ListBox<T> {
         ListBox {
-            title,
+            title: title.into(),
+            theme: Theme::default(),
+            disabled: Box::new(|_| false),
             phantom: PhantomData,
         }
     }
+
+    pub fn theme(mut self, theme: Theme) -> ListBox<T> {
+        self.theme = theme;
+        self
+    }
+
+    /// Greys out the items for which `disabled` returns true.
+    pub fn disabled(mut self, disabled: impl Fn(&T) -> bool + 'static) -> ListBox<T> {
+        self.disabled = Box::new(disabled);
+        self
+    }
+}
+
+impl<T> ListBox<T> {
+    /// Underlines the characters of `text` matched by the filter.
+    fn highlight<'a>(&self, text: &'a str, positions: &[usize]) -> Line<'a> {
+        if positions.is_empty() {
+            return Line::from(text);
+        }
+        let matched = Style::new()
+            .fg(self.theme.highlight)
+            .add_modifier(Modifier::UNDERLINED);
+        let mut spans: Vec<Span> = vec![];
+        for (i, c) in text.chars().enumerate() {
+            let style = match positions.contains(&i) {
+                true => matched,
+                false => Style::new(),
+            };
+            match spans.last_mut() {
+                Some(span) if span.style == style => span.content.to_mut().push(c),
+                _ => spans.push(Span::styled(c.to_string(), style)),
+            }
+        }
+        Line::from(spans)
+    }
 }
 
 impl<T: ListItemProvider> StatefulWidget for &ListBox<T> {
@@ -30,33 +81,76 @@ impl<T: ListItemProvider> StatefulWidget for &ListBox<T> {
         buf: &mut ratatui::prelude::Buffer,
         state: &mut Self::State,
     ) {
+        // The query is shown in the title while filtering.
+        let title = match &state.filter {
+            Some(filter) => format!("{}/{} ", self.title, filter.query),
+            None => self.title.clone(),
+        };
         let block = if state.active {
             Block::bordered()
                 .border_type(BorderType::Double)
-                .border_style(Style::new().light_blue())
-                .title(self.title)
+                .border_style(Style::new().fg(self.theme.active_border))
+                .title(title)
         } else {
             Block::bordered()
                 .border_type(BorderType::Plain)
-                .border_style(Style::new().gray())
-                .title(self.title)
+                .border_style(Style::new().fg(self.theme.border))
+                .title(title)
         };
 
-        let items: Vec<ListItem> = state
-            .list
-            .items
-            .iter()
-            .map(|s| ListItem::new(s.as_str()))
+        let rows: Vec<(&T, &[usize])> = match &state.filter {
+            Some(filter) => filter
+                .matches
+                .iter()
+                .map(|(i, m)| (&state.list.items[*i], m.positions.as_slice()))
+                .collect(),
+            None => state.list.items.iter().map(|s| (s, &[][..])).collect(),
+        };
+        let items: Vec<ListItem> = rows
+            .into_iter()
+            .map(|(s, positions)| {
+                let mut line = self.highlight(s.as_str(), positions);
+                if let Some(icon) = s.icon() {
+                    line.spans.insert(0, icon);
+                    line.spans.insert(1, Span::raw(" "));
+                }
+                if (self.disabled)(s) {
+                    line = line.fg(self.theme.detail);
+                }
+                if let Some(detail) = s.detail() {
+                    line.push_span(Span::styled(
+                        format!("  {detail}"),
+                        Style::new().fg(self.theme.detail),
+                    ));
+                }
+                ListItem::new(line)
+            })
             .collect();
 
+        let list_state = match &mut state.filter {
+            Some(filter) => {
+                let selected = state.list.state.selected();
+                let row = filter
+                    .matches
+                    .iter()
+                    .position(|(i, _)| Some(*i) == selected);
+                filter.state.select(row);
+                &mut filter.state
+            }
+            None => &mut state.list.state,
+        };
         StatefulWidget::render(
             List::new(items)
                 .block(block)
-                .highlight_style(Style::default().add_modifier(Modifier::BOLD).light_green())
+                .highlight_style(
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(self.theme.highlight),
+                )
                 .highlight_symbol(" > "),
             area,
             buf,
-            &mut state.list.state,
+            list_state,
         );
     }
 }
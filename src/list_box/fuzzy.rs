@@ -0,0 +1,155 @@
+//! Subsequence matching for type-to-filter, ranking matches that are
+//! consecutive or start words above scattered ones.
+
+const MATCH: i32 = 16;
+/// The match starts the text or a word, e.g. `s` in `my_server` or `S` in
+/// `MyServer`.
+const BOUNDARY: i32 = 10;
+/// The match follows the previous one.
+const CONSECUTIVE: i32 = 8;
+/// Per character skipped between two matches.
+const GAP: i32 = 1;
+
+/// How well an item matches, and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub score: i32,
+    /// The indices of the matched characters.
+    pub positions: Vec<usize>,
+}
+
+/// Matches the characters of `query` in order in `text`, ignoring case
+/// unless the query has an uppercase letter. Returns the best alignment.
+pub fn score(query: &str, text: &str) -> Option<Match> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| match case_sensitive {
+        true => c,
+        false => c.to_ascii_lowercase(),
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().copied().map(fold).collect();
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: vec![],
+        });
+    }
+    if query.len() > text.len() {
+        return None;
+    }
+
+    // best[i][j]: the best score of query[..=i] with query[i] at text[j],
+    // from[i][j]: where query[i - 1] is then.
+    let mut best = vec![vec![None::<i32>; text.len()]; query.len()];
+    let mut from = vec![vec![0; text.len()]; query.len()];
+    for (i, &q) in query.iter().enumerate() {
+        for j in i..text.len() {
+            if text[j] != q {
+                continue;
+            }
+            let boundary = if is_boundary(&original, j) {
+                BOUNDARY
+            } else {
+                0
+            };
+            let bonus = MATCH + boundary;
+            if i == 0 {
+                best[i][j] = Some(bonus - GAP * j.min(3) as i32);
+                continue;
+            }
+            for k in i - 1..j {
+                let Some(previous) = best[i - 1][k] else {
+                    continue;
+                };
+                let link = match j - k - 1 {
+                    0 => CONSECUTIVE,
+                    gap => -GAP * gap as i32,
+                };
+                let score = previous + bonus + link;
+                if best[i][j].is_none_or(|s| score > s) {
+                    best[i][j] = Some(score);
+                    from[i][j] = k;
+                }
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, s)| s.map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    let mut positions = vec![j; query.len()];
+    for i in (1..query.len()).rev() {
+        j = from[i][j];
+        positions[i - 1] = j;
+    }
+    Some(Match { score, positions })
+}
+
+fn is_boundary(text: &[char], index: usize) -> bool {
+    let Some(previous) = index.checked_sub(1).map(|i| text[i]) else {
+        return true;
+    };
+    let current = text[index];
+    !previous.is_alphanumeric()
+        || (previous.is_lowercase() && current.is_uppercase())
+        || (!previous.is_ascii_digit() && current.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank(query: &str, texts: &[&'static str]) -> Vec<&'static str> {
+        let mut ranked: Vec<_> = texts
+            .iter()
+            .filter_map(|&text| Some((score(query, text)?.score, text)))
+            .collect();
+        ranked.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        ranked.into_iter().map(|(_, text)| text).collect()
+    }
+
+    #[test]
+    fn prefers_consecutive_matches() {
+        assert_eq!(rank("ser", &["xsxexr", "xserver"]), ["xserver", "xsxexr"]);
+        assert_eq!(score("ser", "xserver").unwrap().positions, [1, 2, 3]);
+    }
+
+    #[test]
+    fn prefers_word_boundaries() {
+        assert_eq!(
+            rank("ms", &["mismatch", "my_server"]),
+            ["my_server", "mismatch"]
+        );
+        assert_eq!(
+            rank("ms", &["mismatch", "MyServer"]),
+            ["MyServer", "mismatch"]
+        );
+        assert_eq!(score("ms", "MyServer").unwrap().positions, [0, 2]);
+    }
+
+    #[test]
+    fn prefers_matches_near_the_start() {
+        assert_eq!(
+            rank("app", &["my_app", "app_tests"]),
+            ["app_tests", "my_app"]
+        );
+    }
+
+    #[test]
+    fn matches_case_only_for_uppercase_queries() {
+        assert!(score("server", "MyServer").is_some());
+        assert!(score("Server", "my_server").is_none());
+        assert_eq!(score("S", "sS").unwrap().positions, [1]);
+    }
+
+    #[test]
+    fn needs_every_character_in_order() {
+        assert_eq!(score("abc", "acb"), None);
+        assert_eq!(score("abcd", "abc"), None);
+        assert_eq!(score("", "abc").unwrap().score, 0);
+    }
+}
@@ -1,8 +1,27 @@
+use ratatui::widgets::ListState;
+
+use super::{
+    ListItemProvider,
+    fuzzy::{self, Match},
+};
 use crate::stateful_list::StatefulList;
 
 pub struct ListBoxState<T> {
     pub list: StatefulList<T>,
     pub active: bool,
+    /// The type-to-filter query, if any. The selection stays an index into
+    /// `list.items`, so it does not move as the matches change.
+    pub filter: Option<Filter>,
+}
+
+/// The items matching a fuzzy query, best first.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub query: String,
+    /// The index of each matching item, with the matched characters.
+    pub matches: Vec<(usize, Match)>,
+    /// The row of the selected item among the matches when rendering.
+    pub state: ListState,
 }
 
 impl<T> ListBoxState<T> {
@@ -11,11 +30,84 @@ impl<T> ListBoxState<T> {
     }
 
     pub fn prev(&mut self) {
-        self.list.prev();
+        match &self.filter {
+            Some(filter) => self.step(filter.matches.len().saturating_sub(1)),
+            None => self.list.prev(),
+        }
     }
 
     pub fn next(&mut self) {
-        self.list.next();
+        match &self.filter {
+            Some(_) => self.step(1),
+            None => self.list.next(),
+        }
+    }
+
+    /// Moves `by` rows forward among the matches, wrapping around.
+    fn step(&mut self, by: usize) {
+        let Some(filter) = &self.filter else {
+            return;
+        };
+        if filter.matches.is_empty() {
+            return;
+        }
+        let row = self
+            .list
+            .state
+            .selected()
+            .and_then(|i| filter.matches.iter().position(|(index, _)| *index == i));
+        let row = match row {
+            Some(row) => (row + by) % filter.matches.len(),
+            None => 0,
+        };
+        self.list.state.select(Some(filter.matches[row].0));
+    }
+}
+
+impl<T: ListItemProvider> ListBoxState<T> {
+    /// Shows only the items fuzzy matching `query`, or all of them without
+    /// one. The selected item is kept if it still matches, otherwise the
+    /// best match is selected.
+    pub fn set_filter(&mut self, query: Option<String>) {
+        self.filter = query.map(|query| Filter {
+            query,
+            ..Default::default()
+        });
+        self.refilter();
+        if self.filter.is_none()
+            && self.list.state.selected().is_none()
+            && !self.list.items.is_empty()
+        {
+            self.list.state.select(Some(0));
+        }
+    }
+
+    /// Matches the items again, e.g. after they changed.
+    pub fn refilter(&mut self) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        filter.matches = self
+            .list
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| fuzzy::score(&filter.query, item.as_str()).map(|m| (i, m)))
+            .collect();
+        let items = &self.list.items;
+        // Stable, so equal matches keep the order of the list.
+        filter.matches.sort_by_key(|(i, m)| {
+            (
+                std::cmp::Reverse(m.score),
+                items[*i].as_str().chars().count(),
+            )
+        });
+        let selected = self.list.state.selected();
+        if !filter.matches.iter().any(|(i, _)| Some(*i) == selected) {
+            self.list
+                .state
+                .select(filter.matches.first().map(|(i, _)| *i));
+        }
     }
 }
 
@@ -24,6 +116,7 @@ impl<T> Default for ListBoxState<T> {
         Self {
             active: true,
             list: Default::default(),
+            filter: None,
         }
     }
 }
@@ -0,0 +1,131 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use eyre::{Result, WrapErr};
+use ratatui::text::Line;
+use serde::Deserialize;
+
+use crate::{ansi, utils};
+
+/// Where the output of every job is saved when `output.tee` is set.
+const LOG_DIR: &str = ".cmake-runner/logs";
+
+/// How output is written to a file.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// The text alone.
+    Plain,
+    /// The text with its colors, for `less -R` or `cat`.
+    Ansi,
+}
+
+impl LogFormat {
+    fn line(self, line: &Line) -> String {
+        match self {
+            LogFormat::Plain => line.to_string(),
+            LogFormat::Ansi => ansi::encode(line),
+        }
+    }
+}
+
+/// Creates a file in `dir` named like `20261018-142501-build-app.log`,
+/// sorting by time, and adding a number if it exists.
+fn create(dir: &Path, title: &str) -> Result<(PathBuf, File)> {
+    fs::create_dir_all(dir).wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+    let name = file_name(title, SystemTime::now());
+    let mut path = dir.join(format!("{name}.log"));
+    let mut n = 1;
+    loop {
+        match File::create_new(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                n += 1;
+                path = dir.join(format!("{name}-{n}.log"));
+            }
+            Err(e) => {
+                return Err(e).wrap_err_with(|| format!("failed to create {}", path.display()));
+            }
+        }
+    }
+}
+
+fn file_name(title: &str, time: SystemTime) -> String {
+    let slug: String = title
+        .chars()
+        .map(|c| match c.is_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '-',
+        })
+        .collect();
+    let slug: Vec<_> = slug.split('-').filter(|s| !s.is_empty()).collect();
+    format!("{}-{}", utils::timestamp(time), slug.join("-"))
+}
+
+/// Writes `lines` to a new file in `dir` named after `title`, returning its
+/// path.
+pub fn export<'a>(
+    lines: impl IntoIterator<Item = &'a Line<'static>>,
+    dir: &Path,
+    title: &str,
+    format: LogFormat,
+) -> Result<PathBuf> {
+    let (path, file) = create(dir, title)?;
+    let mut file = BufWriter::new(file);
+    for line in lines {
+        writeln!(file, "{}", format.line(line))?;
+    }
+    file.flush()
+        .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// A copy of a job's output in `.cmake-runner/logs`.
+#[derive(Debug)]
+pub struct Tee {
+    file: BufWriter<File>,
+    format: LogFormat,
+}
+
+impl Tee {
+    /// Starts the log of the job `title`, first deleting the oldest logs so
+    /// that at most `keep` remain.
+    pub fn open(source_dir: &Path, title: &str, format: LogFormat, keep: usize) -> Result<Self> {
+        let dir = source_dir.join(LOG_DIR);
+        if dir.is_dir() {
+            rotate(&dir, keep.saturating_sub(1))?;
+        }
+        let (_, file) = create(&dir, title)?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            format,
+        })
+    }
+
+    pub fn write(&mut self, line: &Line) -> Result<()> {
+        Ok(writeln!(self.file, "{}", self.format.line(line))?)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.file.flush()?)
+    }
+}
+
+/// Deletes the oldest logs in `dir` until at most `keep` remain.
+fn rotate(dir: &Path, keep: usize) -> Result<()> {
+    let mut logs: Vec<_> = fs::read_dir(dir)
+        .wrap_err_with(|| format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "log"))
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep);
+    for path in &logs[..excess] {
+        fs::remove_file(path).wrap_err_with(|| format!("failed to delete {}", path.display()))?;
+    }
+    Ok(())
+}
@@ -1,21 +1,143 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use cmake_runner::app::App;
+use clap::{Parser, Subcommand};
+use cmake_runner::{
+    action::Action,
+    app::App,
+    config,
+    configure::{self, Configure, Generator},
+    ctest,
+    headless::{Format, Headless},
+};
 use eyre::Result;
 
+/// A terminal interface to configure, build, run and test CMake targets.
+/// Without a command, it starts the interface.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// The directory with the top-level CMakeLists.txt.
+    #[arg(default_value = ".")]
+    source_dir: PathBuf,
+    /// The build directory, `build` or the last one used by default.
+    #[arg(short = 'B', long, global = true)]
+    build_dir: Option<PathBuf>,
+    /// Ninja or Unix Makefiles.
+    #[arg(short = 'G', long, global = true, value_parser = parse_generator)]
+    generator: Option<Generator>,
+    /// CMAKE_BUILD_TYPE, e.g. Release.
+    #[arg(long, global = true)]
+    build_type: Option<String>,
+    /// A cache entry, `-D <name>=<value>`.
+    #[arg(short = 'D', value_name = "NAME=VALUE", global = true, value_parser = parse_cache_entry)]
+    define: Vec<(String, String)>,
+    /// How the commands print their results.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: Format,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the targets.
+    List,
+    /// Builds a target.
+    Build { target: String },
+    /// Builds and runs an executable target.
+    Run {
+        target: String,
+        /// The arguments of the program, the saved ones when omitted.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Builds everything and runs the tests.
+    Test {
+        /// Runs only the tests matching a regex.
+        #[arg(short = 'R', long, conflicts_with = "label")]
+        regex: Option<String>,
+        /// Runs only the tests with one of the labels.
+        #[arg(short = 'L', long)]
+        label: Vec<String>,
+    },
+}
+
+fn parse_generator(generator: &str) -> Result<Generator, String> {
+    Generator::parse(generator).ok_or_else(|| format!("unsupported generator: {generator}"))
+}
+
+fn parse_cache_entry(entry: &str) -> Result<(String, String), String> {
+    configure::parse_cache_entry(entry).ok_or_else(|| format!("invalid cache entry: {entry}"))
+}
+
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    let dir = std::env::args().nth(1).unwrap_or(".".to_string());
-    let path = Path::new(&dir)
+async fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    let mut configure = Configure::default();
+    if let Some(generator) = cli.generator {
+        configure.generator = generator;
+    }
+    if let Some(build_type) = cli.build_type {
+        configure.build_type = build_type;
+    }
+    configure.cache_entries = cli.define;
+
+    let path = cli
+        .source_dir
         .canonicalize()
         .ok()
         .map(|p| p.join("CMakeLists.txt"))
         .filter(|p| p.exists())
         .ok_or_else(|| eyre::eyre!("Enter a valid directory containing a CMakeLists.txt file."))?;
+    let build = cli.build_dir.as_deref().map(absolute).transpose()?;
+    let config = config::load(path.parent().unwrap_or(Path::new(".")))?;
+
+    let Some(command) = cli.command else {
+        let mut terminal = ratatui::init();
+        terminal.clear()?;
+        let result = App::new(path, build, configure, config)
+            .run(&mut terminal)
+            .await;
+        ratatui::restore();
+        return result.map(|_| ExitCode::SUCCESS);
+    };
+
+    let headless = Headless::new(path, build, configure, &config, cli.format);
+    let code = match command {
+        Command::List => {
+            headless.list()?;
+            0
+        }
+        Command::Build { target } => {
+            let project = headless.project(&target, &Action::Build)?;
+            headless.run(Action::Build, project).await?
+        }
+        Command::Run { target, args } => {
+            let mut project = headless.project(&target, &Action::Run)?;
+            if !args.is_empty() {
+                project.run.args = args;
+            }
+            headless.run(Action::Run, project).await?
+        }
+        Command::Test { regex, label } => {
+            let filter = match (regex, label) {
+                (Some(regex), _) => ctest::Filter::Regex(regex),
+                (None, labels) if !labels.is_empty() => ctest::Filter::Labels(labels),
+                _ => ctest::Filter::All,
+            };
+            headless
+                .run(Action::Test(filter), Default::default())
+                .await?
+        }
+    };
+    Ok(ExitCode::from(u8::try_from(code).unwrap_or(1)))
+}
 
-    let mut terminal = ratatui::init();
-    terminal.clear()?;
-    let result = App::new(path).run(&mut terminal).await;
-    ratatui::restore();
-    result
+/// Like `canonicalize`, but also works for build directories that do not
+/// exist yet.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    Ok(path.canonicalize().or_else(|_| std::path::absolute(path))?)
 }
@@ -1,48 +1,384 @@
-use eyre::Result;
-use std::{collections::BTreeMap, fs, path::PathBuf};
-
-use crate::project::Project;
-
-pub fn refresh_list(path: &PathBuf) -> Result<Vec<Project>> {
-    let file = fs::read_to_string(path)?;
-    let mut projects: BTreeMap<String, Project> = BTreeMap::new();
-    for line in file.lines() {
-        if line.starts_with("add_executable") {
-            let target = line
-                .split("(")
-                .nth(1)
-                .unwrap()
-                .split(" ")
-                .nth(0)
-                .unwrap()
-                .to_string();
-            projects.insert(
-                target.clone(),
-                Project {
-                    target,
-                    file_name: "".to_string(),
-                },
-            );
-        } else if line.starts_with("set_target_properties") {
-            let target = line
-                .split("(")
-                .nth(1)
-                .unwrap()
-                .split(" ")
-                .nth(0)
-                .unwrap()
-                .to_string();
-            if let Some(element) = projects.get_mut(&target) {
-                element.file_name = line
-                    .split("\"")
-                    .nth(1)
-                    .unwrap()
-                    .split("\"")
-                    .nth(0)
-                    .unwrap()
-                    .to_string();
+use eyre::{Result, WrapErr, eyre};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::project::{Project, TargetKind};
+
+pub mod ast;
+pub mod lexer;
+pub mod scope;
+use ast::{Argument, Command};
+use scope::Scope;
+
+/// Discovers targets starting from the top-level `CMakeLists.txt` at `path`,
+/// following `add_subdirectory` and `include` calls. `build_dir` is used to
+/// resolve `CMAKE_BINARY_DIR` and the output directories of targets.
+pub fn refresh_list(path: &Path, build_dir: &Path) -> Result<Vec<Project>> {
+    let root = path
+        .parent()
+        .ok_or_else(|| eyre!("invalid project path: {}", path.display()))?
+        .to_path_buf();
+
+    let mut scope = Scope::default();
+    scope.set_path("CMAKE_SOURCE_DIR", &root);
+    scope.set_path("PROJECT_SOURCE_DIR", &root);
+    scope.set_path("CMAKE_BINARY_DIR", build_dir);
+    scope.set_path("PROJECT_BINARY_DIR", build_dir);
+
+    let mut walker = Walker {
+        root: root.clone(),
+        build: build_dir.to_path_buf(),
+        projects: BTreeMap::new(),
+        visited: HashSet::new(),
+    };
+    walker.walk_directory(&root, &mut scope)?;
+    Ok(walker.projects.into_values().collect())
+}
+
+struct Walker {
+    root: PathBuf,
+    build: PathBuf,
+    projects: BTreeMap<String, Project>,
+    visited: HashSet<PathBuf>,
+}
+
+impl Walker {
+    fn walk_directory(&mut self, dir: &Path, scope: &mut Scope) -> Result<()> {
+        scope.set_path("CMAKE_CURRENT_SOURCE_DIR", dir);
+        scope.set_path("CMAKE_CURRENT_BINARY_DIR", &self.binary_dir(dir));
+        self.walk_file(&dir.join("CMakeLists.txt"), dir, scope)
+    }
+
+    fn walk_file(&mut self, file: &Path, dir: &Path, scope: &mut Scope) -> Result<()> {
+        let file = &fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        // Guards against include cycles and files pulled in more than once.
+        if !self.visited.insert(file.clone()) {
+            return Ok(());
+        }
+
+        let src = fs::read_to_string(file)
+            .wrap_err_with(|| format!("failed to read {}", file.display()))?;
+        let commands =
+            ast::parse(&src).wrap_err_with(|| format!("failed to parse {}", file.display()))?;
+
+        let list_dir = file.parent().unwrap_or(dir);
+        scope.set_path("CMAKE_CURRENT_LIST_DIR", list_dir);
+        scope.set_path("CMAKE_CURRENT_LIST_FILE", file);
+
+        for command in &commands {
+            self.visit(command, file, dir, scope)
+                .wrap_err_with(|| format!("{}:{}", file.display(), command.line))?;
+        }
+        Ok(())
+    }
+
+    fn visit(
+        &mut self,
+        command: &Command,
+        file: &Path,
+        dir: &Path,
+        scope: &mut Scope,
+    ) -> Result<()> {
+        let args = scope.expand_all(&command.args);
+        let name = command.name.to_ascii_lowercase();
+        match name.as_str() {
+            "set" => {
+                let Some(var) = single(args.first()) else {
+                    return Ok(());
+                };
+                let values: Option<Vec<_>> = args[1..]
+                    .iter()
+                    .take_while(|v| !matches!(single(Some(v)), Some("CACHE" | "PARENT_SCOPE")))
+                    .cloned()
+                    .collect();
+                match values.map(|v| v.concat()) {
+                    Some(values) if !values.is_empty() => scope.set(var, values.join(";")),
+                    // An unknown value would resolve paths wrongly later on.
+                    _ => scope.unset(var),
+                }
+            }
+            "unset" => {
+                if let Some(var) = single(args.first()) {
+                    scope.unset(var);
+                }
+            }
+            "list" => {
+                if single(args.first()) == Some("APPEND")
+                    && let Some(var) = single(args.get(1))
+                {
+                    match args[2..].iter().cloned().collect::<Option<Vec<_>>>() {
+                        Some(items) => scope.append(var, &items.concat()),
+                        None => scope.unset(var),
+                    }
+                }
+            }
+            "project" => {
+                if let Some(project) = single(args.first()) {
+                    scope.set("PROJECT_NAME", project);
+                    scope.set_path("PROJECT_SOURCE_DIR", dir);
+                    scope.set_path(&format!("{project}_SOURCE_DIR"), dir);
+                    scope.set_path("PROJECT_BINARY_DIR", &self.binary_dir(dir));
+                    scope.set_path(&format!("{project}_BINARY_DIR"), &self.binary_dir(dir));
+                }
+            }
+            "add_subdirectory" => {
+                let Some(subdir) = command
+                    .args
+                    .first()
+                    .and_then(|a| scope.expand_str(&a.value))
+                else {
+                    return Ok(());
+                };
+                let subdir = fs::canonicalize(dir.join(&subdir)).unwrap_or(dir.join(subdir));
+                // Conditions are not evaluated, so the directory may be one
+                // that is only added when it exists or an option is set.
+                if !subdir.join("CMakeLists.txt").is_file() {
+                    return Ok(());
+                }
+                // Each directory gets a copy of its parent's variables.
+                let mut child = scope.clone();
+                self.walk_directory(&subdir, &mut child)?;
+            }
+            "include" => {
+                let Some(name) = single(args.first()) else {
+                    return Ok(());
+                };
+                let Some(included) = self.resolve_include(name, dir, scope) else {
+                    return Ok(());
+                };
+                self.walk_file(&included, dir, scope)?;
+                scope.set_path("CMAKE_CURRENT_LIST_DIR", file.parent().unwrap_or(dir));
+                scope.set_path("CMAKE_CURRENT_LIST_FILE", file);
+            }
+            "add_executable" | "add_library" | "add_custom_target" => {
+                let Some((target, kind)) = target(command, scope) else {
+                    return Ok(());
+                };
+                self.projects.insert(
+                    target.clone(),
+                    Project {
+                        target,
+                        kind,
+                        output_dir: scope
+                            .get("CMAKE_RUNTIME_OUTPUT_DIRECTORY")
+                            .map(|d| self.binary_dir(dir).join(d)),
+                        source: self.relative(file),
+                        dir: self.relative(dir),
+                        line: command.line,
+                        ..Default::default()
+                    },
+                );
             }
+            "set_target_properties" => {
+                let targets: Vec<_> = command
+                    .args
+                    .iter()
+                    .take_while(|a| a.value != "PROPERTIES")
+                    .filter_map(|a| scope.expand(a))
+                    .flatten()
+                    .collect();
+                let properties: Vec<_> = properties(&command.args, scope).collect();
+                for target in &targets {
+                    for (name, value) in &properties {
+                        self.set_property(target, name, value, dir);
+                    }
+                }
+            }
+            "set_property" => {
+                // set_property(TARGET <targets>... [APPEND] PROPERTY <name> <value>)
+                let args = &command.args;
+                if args.first().is_none_or(|a| a.value != "TARGET")
+                    || args.iter().any(|a| a.value == "APPEND")
+                {
+                    return Ok(());
+                }
+                let Some(start) = args.iter().position(|a| a.value == "PROPERTY") else {
+                    return Ok(());
+                };
+                let [name, value, ..] = &args[start + 1..] else {
+                    return Ok(());
+                };
+                let (Some(name), Some(value)) = (value_of(name, scope), value_of(value, scope))
+                else {
+                    return Ok(());
+                };
+                let targets = args[1..start].iter().filter_map(|a| scope.expand(a));
+                for target in targets.flatten() {
+                    self.set_property(&target, &name, &value, dir);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Resolves the file loaded by `include(<file|module> [OPTIONAL] ...)`.
+    ///
+    /// Modules are looked up in `CMAKE_MODULE_PATH`; ones that are not found
+    /// there are assumed to be shipped with CMake and skipped. Missing files
+    /// are skipped too: they may be generated by the configure step, e.g.
+    /// `${CMAKE_BINARY_DIR}/conan_paths.cmake`, or behind a condition.
+    fn resolve_include(&self, name: &str, dir: &Path, scope: &Scope) -> Option<PathBuf> {
+        if name.ends_with(".cmake") || name.contains('/') {
+            return Some(dir.join(name)).filter(|p| p.is_file());
         }
+
+        let module_path = scope.get("CMAKE_MODULE_PATH").unwrap_or_default();
+        scope::split_list(module_path)
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| dir.join(p).join(format!("{name}.cmake")))
+            .find(|p| p.is_file())
+    }
+
+    /// Records the target properties that affect where its binary ends up.
+    fn set_property(&mut self, target: &str, name: &str, value: &str, dir: &Path) {
+        let binary_dir = self.binary_dir(dir);
+        let Some(project) = self.projects.get_mut(target) else {
+            return;
+        };
+        match name {
+            "OUTPUT_NAME" | "RUNTIME_OUTPUT_NAME" => project.output_name = Some(value.to_string()),
+            "RUNTIME_OUTPUT_DIRECTORY" => project.output_dir = Some(binary_dir.join(value)),
+            _ => {}
+        }
+    }
+
+    /// The build tree directory mirroring the source directory `dir`.
+    fn binary_dir(&self, dir: &Path) -> PathBuf {
+        self.build.join(self.relative(dir))
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+}
+
+/// Returns the target defined by an `add_executable`, `add_library` or
+/// `add_custom_target` call, skipping `IMPORTED` and `ALIAS` targets.
+fn target(command: &Command, scope: &Scope) -> Option<(String, TargetKind)> {
+    let target = scope.expand_str(command.arg(0)?)?;
+    let rest = &command.args[1..];
+    let imported = rest
+        .iter()
+        .take(2)
+        .any(|a| a.value == "IMPORTED" || a.value == "ALIAS");
+    if imported && !command.is("add_custom_target") {
+        return None;
+    }
+
+    let kind = if command.is("add_executable") {
+        TargetKind::Executable
+    } else if command.is("add_custom_target") {
+        TargetKind::Custom
+    } else {
+        match rest
+            .first()
+            .and_then(|a| TargetKind::from_library_keyword(&a.value))
+        {
+            Some(kind) => kind,
+            None if is_on(scope.get("BUILD_SHARED_LIBS")) => TargetKind::SharedLibrary,
+            None => TargetKind::StaticLibrary,
+        }
+    };
+    Some((target, kind))
+}
+
+/// Evaluates a CMake boolean constant.
+fn is_on(value: Option<&str>) -> bool {
+    value.is_some_and(|v| {
+        matches!(
+            v.to_ascii_uppercase().as_str(),
+            "1" | "ON" | "YES" | "TRUE" | "Y"
+        )
+    })
+}
+
+/// Returns the key/value pairs following `PROPERTIES` in a
+/// `set_target_properties` call. The arguments are paired before being
+/// expanded, so a value that cannot be resolved only drops its own pair.
+fn properties<'a>(
+    args: &'a [Argument],
+    scope: &'a Scope,
+) -> impl Iterator<Item = (String, String)> + 'a {
+    let start = args
+        .iter()
+        .position(|a| a.value == "PROPERTIES")
+        .map_or(args.len(), |i| i + 1);
+    args[start..]
+        .chunks_exact(2)
+        .filter_map(|pair| Some((value_of(&pair[0], scope)?, value_of(&pair[1], scope)?)))
+}
+
+/// The value of an expanded argument that is a single item, e.g. a variable
+/// name.
+fn single(arg: Option<&Option<Vec<String>>>) -> Option<&str> {
+    match arg?.as_deref()? {
+        [value] => Some(value),
+        _ => None,
+    }
+}
+
+/// Expands a single argument, joining the items of a list.
+fn value_of(arg: &Argument, scope: &Scope) -> Option<String> {
+    scope.expand(arg).map(|items| items.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The targets of `tests/fixtures/parser/<name>`, built in `/build`.
+    fn fixture(name: &str) -> Vec<Project> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/parser")
+            .join(name)
+            .join("CMakeLists.txt");
+        refresh_list(&path, Path::new("/build")).unwrap()
+    }
+
+    fn find<'a>(projects: &'a [Project], target: &str) -> &'a Project {
+        projects.iter().find(|p| p.target == target).unwrap()
+    }
+
+    #[test]
+    fn skips_only_the_properties_that_do_not_resolve() {
+        let projects = fixture("properties");
+        let app = find(&projects, "app");
+        assert_eq!(app.output_name, None);
+        assert_eq!(app.output_dir.as_deref(), Some(Path::new("/build/bin")));
+
+        let tool = find(&projects, "tool");
+        assert_eq!(tool.output_name.as_deref(), Some("my-tool"));
+        assert_eq!(tool.output_dir.as_deref(), Some(Path::new("/build/out")));
+
+        let other = find(&projects, "other");
+        assert_eq!(other.output_name, None);
+        assert_eq!(
+            other.output_dir.as_deref(),
+            Some(Path::new("/build/out/other"))
+        );
+    }
+
+    #[test]
+    fn follows_subdirectories_and_includes() {
+        let projects = fixture("traversal");
+        let targets: Vec<_> = projects
+            .iter()
+            .map(|p| (p.target.as_str(), p.kind, p.source.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("core", TargetKind::SharedLibrary, "lib/CMakeLists.txt"),
+                ("docs", TargetKind::Custom, "cmake/extra.cmake"),
+                ("helpers", TargetKind::StaticLibrary, "cmake/Targets.cmake"),
+                ("traversal", TargetKind::Executable, "CMakeLists.txt"),
+            ]
+        );
+        assert_eq!(find(&projects, "core").dir, Path::new("lib"));
     }
-    Ok(projects.into_values().collect())
 }
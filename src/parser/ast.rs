@@ -0,0 +1,130 @@
+use eyre::{Result, eyre};
+
+use super::lexer::{Lexer, Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    Bracket,
+    Quoted,
+    Unquoted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Argument {
+    pub value: String,
+    pub kind: ArgumentKind,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub line: usize,
+}
+
+impl Command {
+    /// Command names are case-insensitive in CMake.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(|a| a.value.as_str())
+    }
+}
+
+pub fn parse(src: &str) -> Result<Vec<Command>> {
+    let mut tokens = Lexer::new(src).tokenize()?.into_iter();
+    let mut commands = Vec::new();
+
+    while let Some(Token { kind, line }) = tokens.next() {
+        let TokenKind::Identifier(name) = kind else {
+            return Err(eyre!("line {line}: expected a command name"));
+        };
+        match tokens.next() {
+            Some(Token {
+                kind: TokenKind::Open,
+                ..
+            }) => {}
+            _ => return Err(eyre!("line {line}: expected '(' after '{name}'")),
+        }
+
+        let mut args = Vec::new();
+        let mut depth = 1;
+        for Token { kind, line } in tokens.by_ref() {
+            match kind {
+                TokenKind::Argument(arg) => args.push(arg),
+                // Nested parentheses are passed through as plain arguments,
+                // like CMake does for `if((A OR B) AND C)`.
+                TokenKind::Open => {
+                    depth += 1;
+                    args.push(paren("(", line));
+                }
+                TokenKind::Close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    args.push(paren(")", line));
+                }
+                TokenKind::Identifier(_) => {
+                    unreachable!("identifiers only appear outside arguments")
+                }
+            }
+        }
+        commands.push(Command { name, args, line });
+    }
+    Ok(commands)
+}
+
+fn paren(value: &str, line: usize) -> Argument {
+    Argument {
+        value: value.to_string(),
+        kind: ArgumentKind::Unquoted,
+        line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(command: &Command) -> Vec<&str> {
+        command.args.iter().map(|a| a.value.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_commands_across_lines() {
+        let commands = parse("project(demo)\n\nadd_executable(\n  foo\n  main.c)\n").unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(commands[1].is("add_executable"));
+        assert_eq!(commands[1].line, 3);
+        assert_eq!(values(&commands[1]), ["foo", "main.c"]);
+        assert_eq!(commands[1].args[0].line, 4);
+    }
+
+    #[test]
+    fn allows_space_before_parenthesis() {
+        let commands = parse("add_executable (foo main.c)").unwrap();
+        assert_eq!(commands[0].arg(0), Some("foo"));
+        assert!(parse("add_executable (foo").is_err());
+        assert!(parse("add_executable foo").is_err());
+    }
+
+    #[test]
+    fn matches_names_ignoring_case() {
+        let commands = parse("ADD_EXECUTABLE(\"my app\" main.c)").unwrap();
+        assert!(commands[0].is("add_executable"));
+        assert_eq!(commands[0].args[0].kind, ArgumentKind::Quoted);
+        assert_eq!(commands[0].arg(0), Some("my app"));
+    }
+
+    #[test]
+    fn passes_nested_parentheses_through() {
+        let commands = parse("if((A OR B) AND C)\nendif()").unwrap();
+        assert_eq!(values(&commands[0]), ["(", "A", "OR", "B", ")", "AND", "C"]);
+        assert!(commands[1].is("endif"));
+        assert!(commands[1].args.is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+use eyre::{Result, eyre};
+
+use super::ast::{Argument, ArgumentKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier(String),
+    Open,
+    Close,
+    Argument(Argument),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+}
+
+/// Splits CMake source into command names, parentheses and arguments.
+///
+/// Follows the grammar from `cmake-language(7)`: bracket arguments and
+/// comments (`[==[ ... ]==]`, `#[[ ... ]]`), quoted arguments with escape
+/// sequences and line continuations, and unquoted arguments.
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    line: usize,
+    depth: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            // Editors on Windows may start the file with a byte order mark.
+            src: src.strip_prefix('\u{feff}').unwrap_or(src),
+            pos: 0,
+            line: 1,
+            depth: 0,
+        }
+    }
+
+    pub fn tokenize(mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        if self.depth > 0 {
+            return Err(eyre!("line {}: missing closing ')'", self.line));
+        }
+        Ok(tokens)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        self.skip_trivia()?;
+        let line = self.line;
+        let Some(c) = self.peek() else {
+            return Ok(None);
+        };
+
+        let kind = if self.depth == 0 {
+            match c {
+                '(' => {
+                    self.bump();
+                    self.depth += 1;
+                    TokenKind::Open
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    TokenKind::Identifier(self.identifier())
+                }
+                c => return Err(eyre!("line {line}: unexpected character '{c}'")),
+            }
+        } else {
+            match c {
+                '(' => {
+                    self.bump();
+                    self.depth += 1;
+                    TokenKind::Open
+                }
+                ')' => {
+                    self.bump();
+                    self.depth -= 1;
+                    TokenKind::Close
+                }
+                '"' => TokenKind::Argument(self.quoted()?),
+                '[' if self.bracket_level().is_some() => TokenKind::Argument(self.bracket()?),
+                _ => TokenKind::Argument(self.unquoted()?),
+            }
+        };
+        Ok(Some(Token { kind, line }))
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '#' {
+                self.bump();
+                if let Some(level) = self.bracket_level() {
+                    let line = self.line;
+                    self.skip_bracket_open(level);
+                    self.bracket_body(level)
+                        .ok_or_else(|| eyre!("line {line}: unterminated bracket comment"))?;
+                } else {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        self.src[start..self.pos].to_string()
+    }
+
+    /// Returns the number of `=` of a bracket opening (`[`, `=`*, `[`) at the
+    /// current position, without consuming it.
+    fn bracket_level(&self) -> Option<usize> {
+        let rest = self.src[self.pos..].strip_prefix('[')?;
+        let level = rest.chars().take_while(|&c| c == '=').count();
+        rest[level..].starts_with('[').then_some(level)
+    }
+
+    fn skip_bracket_open(&mut self, level: usize) {
+        for _ in 0..level + 2 {
+            self.bump();
+        }
+    }
+
+    /// Consumes everything up to and including the matching bracket close.
+    fn bracket_body(&mut self, level: usize) -> Option<String> {
+        let close = format!("]{}]", "=".repeat(level));
+        let end = self.src[self.pos..].find(&close)?;
+        let body = self.src[self.pos..self.pos + end].to_string();
+        let stop = self.pos + end + close.len();
+        while self.pos < stop {
+            self.bump();
+        }
+        Some(body)
+    }
+
+    fn bracket(&mut self) -> Result<Argument> {
+        let line = self.line;
+        let level = self.bracket_level().unwrap_or_default();
+        self.skip_bracket_open(level);
+        let body = self
+            .bracket_body(level)
+            .ok_or_else(|| eyre!("line {line}: unterminated bracket argument"))?;
+        let value = body
+            .strip_prefix("\r\n")
+            .or_else(|| body.strip_prefix('\n'))
+            .unwrap_or(&body)
+            .to_string();
+        Ok(Argument {
+            value,
+            kind: ArgumentKind::Bracket,
+            line,
+        })
+    }
+
+    fn quoted(&mut self) -> Result<Argument> {
+        let line = self.line;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => match self.peek() {
+                    Some('\n') => {
+                        self.bump();
+                    }
+                    Some('\r') if self.peek_nth(1) == Some('\n') => {
+                        self.bump();
+                        self.bump();
+                    }
+                    _ => self.escape(&mut value)?,
+                },
+                Some(c) => value.push(c),
+                None => return Err(eyre!("line {line}: unterminated quoted argument")),
+            }
+        }
+        Ok(Argument {
+            value,
+            kind: ArgumentKind::Quoted,
+            line,
+        })
+    }
+
+    fn unquoted(&mut self) -> Result<Argument> {
+        let line = self.line;
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                c if c.is_whitespace() => break,
+                '(' | ')' | '#' => break,
+                '\\' => {
+                    self.bump();
+                    self.escape(&mut value)?;
+                }
+                // Legacy unquoted arguments may embed quoted sections, e.g. `-DFOO="a b"`.
+                '"' => {
+                    value.push(c);
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some('"') => {
+                                value.push('"');
+                                break;
+                            }
+                            Some(c) => value.push(c),
+                            None => {
+                                return Err(eyre!("line {line}: unterminated quoted argument"));
+                            }
+                        }
+                    }
+                }
+                c => {
+                    value.push(c);
+                    self.bump();
+                }
+            }
+        }
+        Ok(Argument {
+            value,
+            kind: ArgumentKind::Unquoted,
+            line,
+        })
+    }
+
+    /// Decodes the escape sequence following a consumed `\`.
+    fn escape(&mut self, value: &mut String) -> Result<()> {
+        let line = self.line;
+        match self.bump() {
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('n') => value.push('\n'),
+            Some(';') => value.push_str("\\;"),
+            Some(c) if !c.is_ascii_alphanumeric() => value.push(c),
+            Some(c) => return Err(eyre!("line {line}: invalid escape sequence '\\{c}'")),
+            None => return Err(eyre!("line {line}: unexpected end of file after '\\'")),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The arguments, as `(value, kind)`, ignoring names and parentheses.
+    fn args(src: &str) -> Vec<(String, ArgumentKind)> {
+        Lexer::new(src)
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter_map(|t| match t.kind {
+                TokenKind::Argument(a) => Some((a.value, a.kind)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skips_comments() {
+        let src = "#[[ add_executable(hidden\n) ]]\n# line comment (\nfoo(a) # trailing";
+        let tokens = Lexer::new(src).tokenize().unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::Identifier("foo".into()));
+        assert_eq!(tokens[0].line, 4);
+        assert_eq!(args(src), [("a".into(), ArgumentKind::Unquoted)]);
+    }
+
+    #[test]
+    fn reads_bracket_arguments() {
+        assert_eq!(
+            args("foo([=[a]]b]=] [[\nx\ny]])"),
+            [
+                ("a]]b".into(), ArgumentKind::Bracket),
+                ("x\ny".into(), ArgumentKind::Bracket),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_escapes_and_continuations() {
+        assert_eq!(
+            args(
+                r#"foo("a\tb\"c\
+d" x\ y "e;f\;")"#
+            ),
+            [
+                ("a\tb\"cd".into(), ArgumentKind::Quoted),
+                ("x y".into(), ArgumentKind::Unquoted),
+                (r"e;f\;".into(), ArgumentKind::Quoted),
+            ]
+        );
+        assert!(Lexer::new("foo(\\q)").tokenize().is_err());
+    }
+
+    #[test]
+    fn keeps_quotes_inside_unquoted_arguments() {
+        assert_eq!(
+            args("foo(-DFOO=\"a b\")"),
+            [("-DFOO=\"a b\"".into(), ArgumentKind::Unquoted)]
+        );
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark() {
+        let tokens = Lexer::new("\u{feff}foo(a)").tokenize().unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::Identifier("foo".into()));
+    }
+
+    #[test]
+    fn counts_lines() {
+        let tokens = Lexer::new("foo(\n  \"a\nb\"\n  c)").tokenize().unwrap();
+        let lines: Vec<_> = tokens.iter().map(|t| t.line).collect();
+        assert_eq!(lines, [1, 1, 2, 4, 4]);
+    }
+
+    #[test]
+    fn fails_on_unterminated_input() {
+        for src in ["foo(a", "foo(\"a)", "foo([[a)", "#[[ comment"] {
+            assert!(Lexer::new(src).tokenize().is_err(), "{src}");
+        }
+    }
+}
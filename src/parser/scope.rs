@@ -0,0 +1,191 @@
+use std::{collections::HashMap, path::Path};
+
+use super::ast::{Argument, ArgumentKind};
+
+/// A minimal CMake variable scope, enough to resolve the paths passed to
+/// `add_subdirectory`, `include` and target properties.
+#[derive(Default, Clone, Debug)]
+pub struct Scope {
+    vars: HashMap<String, String>,
+}
+
+impl Scope {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|v| v.as_str())
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        self.vars.insert(name.to_string(), value.into());
+    }
+
+    pub fn set_path(&mut self, name: &str, value: &Path) {
+        self.set(name, value.to_string_lossy());
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// Appends `items` to the list variable `name`.
+    pub fn append(&mut self, name: &str, items: &[String]) {
+        let value = self.vars.entry(name.to_string()).or_default();
+        for item in items {
+            if !value.is_empty() {
+                value.push(';');
+            }
+            value.push_str(item);
+        }
+    }
+
+    /// Expands an argument the way CMake would, splitting unquoted arguments
+    /// on `;`. Returns `None` if it references a variable this scope does not
+    /// know about.
+    pub fn expand(&self, arg: &Argument) -> Option<Vec<String>> {
+        match arg.kind {
+            ArgumentKind::Bracket => Some(vec![arg.value.clone()]),
+            ArgumentKind::Quoted => Some(vec![self.expand_str(&arg.value)?]),
+            ArgumentKind::Unquoted => Some(
+                split_list(&self.expand_str(&arg.value)?)
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Expands every argument on its own, so that the expansions keep the
+    /// positions of `args` even when some cannot be resolved.
+    pub fn expand_all(&self, args: &[Argument]) -> Vec<Option<Vec<String>>> {
+        args.iter().map(|a| self.expand(a)).collect()
+    }
+
+    /// Replaces `${VAR}` and `$ENV{VAR}` references in a single left to
+    /// right pass, like CMake: names may contain references, e.g.
+    /// `${${PROJECT_NAME}_SOURCE_DIR}`, but values are not expanded again.
+    pub fn expand_str(&self, value: &str) -> Option<String> {
+        self.expand_from(value, false).map(|(expanded, _)| expanded)
+    }
+
+    /// Expands `value` up to its end, or up to the `}` closing a reference
+    /// when `in_reference`, returning the expansion and what follows.
+    fn expand_from<'a>(&self, mut value: &'a str, in_reference: bool) -> Option<(String, &'a str)> {
+        let mut expanded = String::new();
+        loop {
+            let Some(i) = value.find(['$', '}']) else {
+                if in_reference {
+                    return None;
+                }
+                expanded.push_str(value);
+                return Some((expanded, ""));
+            };
+            expanded.push_str(&value[..i]);
+            let rest = &value[i..];
+            if let Some(rest) = rest.strip_prefix('}') {
+                if in_reference {
+                    return Some((expanded, rest));
+                }
+                expanded.push('}');
+                value = rest;
+                continue;
+            }
+            let (is_env, name) = if let Some(name) = rest.strip_prefix("${") {
+                (false, name)
+            } else if let Some(name) = rest.strip_prefix("$ENV{") {
+                (true, name)
+            } else {
+                expanded.push('$');
+                value = &rest[1..];
+                continue;
+            };
+            let (name, rest) = self.expand_from(name, true)?;
+            match is_env {
+                true => expanded.push_str(&std::env::var(&name).ok()?),
+                false => expanded.push_str(self.get(&name)?),
+            }
+            value = rest;
+        }
+    }
+}
+
+/// Splits a CMake list on unescaped `;`.
+pub fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&';') => {
+                chars.next();
+                current.push(';');
+            }
+            ';' => items.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(vars: &[(&str, &str)]) -> Scope {
+        let mut scope = Scope::default();
+        for (name, value) in vars {
+            scope.set(name, *value);
+        }
+        scope
+    }
+
+    #[test]
+    fn expands_nested_references() {
+        let scope = scope(&[("N", "app"), ("app_DIR", "src")]);
+        assert_eq!(scope.expand_str("${${N}_DIR}/x").as_deref(), Some("src/x"));
+    }
+
+    #[test]
+    fn does_not_expand_values_again() {
+        let scope = scope(&[("FMT", "${FMT}")]);
+        assert_eq!(
+            scope.expand_str("a ${FMT} b").as_deref(),
+            Some("a ${FMT} b")
+        );
+    }
+
+    #[test]
+    fn keeps_stray_dollars_and_braces() {
+        let scope = scope(&[("A", "1")]);
+        assert_eq!(scope.expand_str("$x}${A}$").as_deref(), Some("$x}1$"));
+    }
+
+    #[test]
+    fn fails_on_unknown_or_unterminated_references() {
+        let scope = scope(&[]);
+        assert_eq!(scope.expand_str("${MISSING}"), None);
+        assert_eq!(scope.expand_str("${A"), None);
+    }
+
+    #[test]
+    fn keeps_the_positions_of_unresolved_arguments() {
+        let scope = scope(&[("SRCS", "a.c;b.c")]);
+        let arg = |value: &str, kind| Argument {
+            value: value.to_string(),
+            kind,
+            line: 1,
+        };
+        let args = [
+            arg("${MISSING}", ArgumentKind::Unquoted),
+            arg("${SRCS}", ArgumentKind::Unquoted),
+            arg("${SRCS}", ArgumentKind::Quoted),
+        ];
+        assert_eq!(
+            scope.expand_all(&args),
+            [
+                None,
+                Some(vec!["a.c".to_string(), "b.c".to_string()]),
+                Some(vec!["a.c;b.c".to_string()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Result, WrapErr, eyre};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::list_box::ListItemProvider;
+
+const FILES: [&str; 2] = ["CMakePresets.json", "CMakeUserPresets.json"];
+
+/// Fields that `inherits` does not pass down, see `cmake-presets(7)`.
+const NOT_INHERITED: [&str; 5] = ["name", "hidden", "inherits", "description", "displayName"];
+
+/// Fields whose maps are merged with the parents' instead of replacing them.
+const MERGED: [&str; 2] = ["cacheVariables", "environment"];
+
+/// A visible configure preset, with the build and test presets that refer to it.
+#[derive(Default, Clone, Debug)]
+pub struct ConfigurePreset {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub binary_dir: Option<PathBuf>,
+    /// The `environment` with its macros expanded, also set for the
+    /// processes started without `--preset`.
+    pub environment: BTreeMap<String, String>,
+    pub build_preset: Option<String>,
+    /// The `configuration` the build preset builds with a multi-config
+    /// generator.
+    pub configuration: Option<String>,
+    pub test_preset: Option<String>,
+}
+
+impl ListItemProvider for ConfigurePreset {
+    fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    fn detail(&self) -> Option<String> {
+        self.display_name.clone()
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct PresetsFile {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    configure_presets: Vec<Map<String, Value>>,
+    #[serde(default)]
+    build_presets: Vec<Map<String, Value>>,
+    #[serde(default)]
+    test_presets: Vec<Map<String, Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Resolved {
+    name: String,
+    #[serde(default)]
+    hidden: bool,
+    display_name: Option<String>,
+    generator: Option<String>,
+    binary_dir: Option<String>,
+    configure_preset: Option<String>,
+    configuration: Option<String>,
+    #[serde(default)]
+    environment: BTreeMap<String, Option<String>>,
+    condition: Option<Value>,
+}
+
+/// A raw preset object along with the directory of the file defining it,
+/// which `${fileDir}` expands to.
+struct Raw {
+    fields: Map<String, Value>,
+    file_dir: PathBuf,
+}
+
+#[derive(Default)]
+struct Section {
+    presets: HashMap<String, Raw>,
+    order: Vec<String>,
+}
+
+impl Section {
+    fn add(&mut self, fields: Map<String, Value>, file_dir: &Path) -> Result<()> {
+        let name = fields
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| eyre!("preset without a name"))?
+            .to_string();
+        if self.presets.contains_key(&name) {
+            return Err(eyre!("duplicate preset `{name}`"));
+        }
+        self.order.push(name.clone());
+        self.presets.insert(
+            name,
+            Raw {
+                fields,
+                file_dir: file_dir.to_path_buf(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Flattens the `inherits` chain of `name` into a single object.
+    fn flatten(&self, name: &str, seen: &mut HashSet<String>) -> Result<Map<String, Value>> {
+        if !seen.insert(name.to_string()) {
+            return Err(eyre!("preset `{name}` inherits from itself"));
+        }
+        let raw = self
+            .presets
+            .get(name)
+            .ok_or_else(|| eyre!("unknown preset `{name}`"))?;
+        let mut fields = raw.fields.clone();
+
+        let parents = match raw.fields.get("inherits") {
+            Some(Value::String(parent)) => vec![parent.clone()],
+            Some(Value::Array(parents)) => parents
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect(),
+            _ => vec![],
+        };
+        // Earlier parents take precedence over later ones.
+        for parent in parents {
+            let parent = self.flatten(&parent, &mut seen.clone())?;
+            for (key, value) in parent {
+                if NOT_INHERITED.contains(&key.as_str()) {
+                    continue;
+                }
+                match (fields.get_mut(&key), value) {
+                    (Some(Value::Object(own)), Value::Object(inherited))
+                        if MERGED.contains(&key.as_str()) =>
+                    {
+                        for (k, v) in inherited {
+                            own.entry(k).or_insert(v);
+                        }
+                    }
+                    (Some(_), _) => {}
+                    (None, value) => {
+                        fields.insert(key, value);
+                    }
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Resolves every visible preset whose condition holds.
+    fn resolve(&self, source_dir: &Path) -> Result<Vec<(Resolved, Macros)>> {
+        let mut resolved = Vec::new();
+        for name in &self.order {
+            let fields = self.flatten(name, &mut HashSet::new())?;
+            let preset: Resolved = serde_json::from_value(Value::Object(fields))
+                .wrap_err_with(|| format!("invalid preset `{name}`"))?;
+            if preset.hidden {
+                continue;
+            }
+            let macros = Macros::new(source_dir, &self.presets[name].file_dir, &preset);
+            let enabled = match &preset.condition {
+                Some(condition) => macros.condition(condition)?,
+                None => true,
+            };
+            if enabled {
+                resolved.push((preset, macros));
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// Loads `CMakePresets.json` and `CMakeUserPresets.json` from `source_dir`.
+///
+/// Returns the configure presets that are visible on this host, each linked
+/// to the first build and test preset that uses it.
+pub fn load(source_dir: &Path) -> Result<Vec<ConfigurePreset>> {
+    let mut configure = Section::default();
+    let mut build = Section::default();
+    let mut test = Section::default();
+    let mut loaded = HashSet::new();
+    for file in FILES {
+        let path = source_dir.join(file);
+        if path.is_file() {
+            read(&path, &mut loaded, &mut configure, &mut build, &mut test)?;
+        }
+    }
+
+    let builds = build.resolve(source_dir)?;
+    let tests = test.resolve(source_dir)?;
+    Ok(configure
+        .resolve(source_dir)?
+        .into_iter()
+        .map(|(preset, macros)| ConfigurePreset {
+            build_preset: linked(&builds, &preset.name).map(|p| p.name.clone()),
+            configuration: linked(&builds, &preset.name).and_then(|p| p.configuration.clone()),
+            test_preset: linked(&tests, &preset.name).map(|p| p.name.clone()),
+            binary_dir: preset.binary_dir.as_ref().map(|dir| {
+                let dir = PathBuf::from(macros.expand(dir));
+                source_dir.join(dir)
+            }),
+            environment: macros.environment,
+            name: preset.name,
+            display_name: preset.display_name,
+        })
+        .collect())
+}
+
+/// The first of the build or test `presets` that uses the configure preset
+/// `name`.
+fn linked<'a>(presets: &'a [(Resolved, Macros)], name: &str) -> Option<&'a Resolved> {
+    presets
+        .iter()
+        .map(|(p, _)| p)
+        .find(|p| p.configure_preset.as_deref() == Some(name))
+}
+
+fn read(
+    path: &Path,
+    loaded: &mut HashSet<PathBuf>,
+    configure: &mut Section,
+    build: &mut Section,
+    test: &mut Section,
+) -> Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if !loaded.insert(path.clone()) {
+        return Ok(());
+    }
+    let file = fs::read(&path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    let file: PresetsFile = serde_json::from_slice(&file)
+        .wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    for include in &file.include {
+        read(&dir.join(include), loaded, configure, build, test)?;
+    }
+    let sections = [
+        (file.configure_presets, &mut *configure),
+        (file.build_presets, &mut *build),
+        (file.test_presets, &mut *test),
+    ];
+    for (presets, section) in sections {
+        for preset in presets {
+            section
+                .add(preset, dir)
+                .wrap_err_with(|| format!("in {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Expands the macros allowed in preset fields, such as `${sourceDir}` and
+/// `$env{NAME}`.
+struct Macros {
+    values: HashMap<&'static str, String>,
+    environment: BTreeMap<String, String>,
+}
+
+impl Macros {
+    fn new(source_dir: &Path, file_dir: &Path, preset: &Resolved) -> Self {
+        let host = match std::env::consts::OS {
+            "linux" => "Linux",
+            "macos" => "Darwin",
+            "windows" => "Windows",
+            other => other,
+        };
+        let values = HashMap::from([
+            ("sourceDir", source_dir.to_string_lossy().into_owned()),
+            (
+                "sourceParentDir",
+                source_dir
+                    .parent()
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+            (
+                "sourceDirName",
+                source_dir
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+            ("fileDir", file_dir.to_string_lossy().into_owned()),
+            ("presetName", preset.name.clone()),
+            ("generator", preset.generator.clone().unwrap_or_default()),
+            ("hostSystemName", host.to_string()),
+            ("dollar", "$".to_string()),
+            (
+                "pathListSep",
+                if cfg!(windows) { ";" } else { ":" }.to_string(),
+            ),
+        ]);
+        let mut macros = Self {
+            values,
+            environment: BTreeMap::new(),
+        };
+        macros.environment = preset
+            .environment
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), v.clone()?)))
+            .collect();
+        // Entries may refer to each other with `$env{}`, a chain of them takes
+        // one pass per link.
+        for _ in 0..macros.environment.len() {
+            let expanded: BTreeMap<_, _> = macros
+                .environment
+                .iter()
+                .map(|(k, v)| (k.clone(), macros.expand(v)))
+                .collect();
+            if expanded == macros.environment {
+                break;
+            }
+            macros.environment = expanded;
+        }
+        macros
+    }
+
+    fn expand(&self, value: &str) -> String {
+        let mut out = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some((namespace, name, len)) = parse_macro(rest) else {
+                out.push('$');
+                rest = &rest[1..];
+                continue;
+            };
+            let value = match namespace {
+                "" => self.values.get(name).cloned(),
+                "env" => self
+                    .environment
+                    .get(name)
+                    .cloned()
+                    .or_else(|| std::env::var(name).ok()),
+                "penv" => std::env::var(name).ok(),
+                _ => None,
+            };
+            out.push_str(&value.unwrap_or_default());
+            rest = &rest[len..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn condition(&self, condition: &Value) -> Result<bool> {
+        let condition = match condition {
+            Value::Null => return Ok(true),
+            Value::Bool(value) => return Ok(*value),
+            Value::Object(condition) => condition,
+            _ => return Err(eyre!("invalid condition: {condition}")),
+        };
+        let string = |key: &str| -> Result<String> {
+            condition
+                .get(key)
+                .and_then(Value::as_str)
+                .map(|s| self.expand(s))
+                .ok_or_else(|| eyre!("condition is missing `{key}`"))
+        };
+        let list = |key: &str| -> Vec<Value> {
+            condition
+                .get(key)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let kind = condition
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        Ok(match kind {
+            "const" => condition
+                .get("value")
+                .and_then(Value::as_bool)
+                .ok_or_else(|| eyre!("condition is missing `value`"))?,
+            "equals" => string("lhs")? == string("rhs")?,
+            "notEquals" => string("lhs")? != string("rhs")?,
+            "inList" | "notInList" => {
+                let value = string("string")?;
+                let found = list("list")
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|item| self.expand(item) == value);
+                found == (kind == "inList")
+            }
+            "matches" | "notMatches" => {
+                let regex = Regex::new(&string("regex")?)?;
+                regex.is_match(&string("string")?) == (kind == "matches")
+            }
+            "anyOf" => {
+                let mut any = false;
+                for c in list("conditions") {
+                    any |= self.condition(&c)?;
+                }
+                any
+            }
+            "allOf" => {
+                let mut all = true;
+                for c in list("conditions") {
+                    all &= self.condition(&c)?;
+                }
+                all
+            }
+            "not" => !self.condition(condition.get("condition").unwrap_or(&Value::Null))?,
+            other => return Err(eyre!("unknown condition type `{other}`")),
+        })
+    }
+}
+
+/// Splits `$namespace{name}` at the start of `s`, returning the namespace,
+/// the name and the length of the whole macro.
+fn parse_macro(s: &str) -> Option<(&str, &str, usize)> {
+    let open = s.find('{')?;
+    let namespace = &s[1..open];
+    if !namespace.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let close = open + s[open..].find('}')?;
+    Some((namespace, &s[open + 1..close], close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn section(presets: Value) -> Section {
+        let mut section = Section::default();
+        for preset in presets.as_array().unwrap() {
+            let fields = preset.as_object().unwrap().clone();
+            section.add(fields, Path::new("/src")).unwrap();
+        }
+        section
+    }
+
+    fn names(section: &Section) -> Vec<String> {
+        let resolved = section.resolve(Path::new("/src")).unwrap();
+        resolved.into_iter().map(|(p, _)| p.name).collect()
+    }
+
+    #[test]
+    fn inherits_fields_from_parents() {
+        let section = section(json!([
+            {"name": "base", "hidden": true, "binaryDir": "base",
+             "environment": {"A": "base", "B": "base"}},
+            {"name": "other", "hidden": true, "binaryDir": "other", "generator": "Ninja"},
+            {"name": "dev", "inherits": ["base", "other"], "environment": {"A": "dev"}},
+        ]));
+        let dev = section.flatten("dev", &mut HashSet::new()).unwrap();
+        assert_eq!(dev["binaryDir"], "base");
+        assert_eq!(dev["generator"], "Ninja");
+        assert_eq!(dev["environment"], json!({"A": "dev", "B": "base"}));
+        assert!(!dev.contains_key("hidden"));
+        assert_eq!(names(&section), ["dev"]);
+    }
+
+    #[test]
+    fn fails_on_missing_or_cyclic_parents() {
+        let missing = section(json!([{"name": "dev", "inherits": "base"}]));
+        assert!(missing.resolve(Path::new("/src")).is_err());
+
+        let cycle = section(json!([
+            {"name": "a", "inherits": "b"},
+            {"name": "b", "inherits": "a"},
+        ]));
+        assert!(cycle.resolve(Path::new("/src")).is_err());
+    }
+
+    #[test]
+    fn expands_macros() {
+        let section = section(json!([{
+            "name": "dev",
+            "environment": {"FOO": "foo", "BAR": "$env{FOO}-$penv{FOO}"},
+        }]));
+        let (_, macros) = section.resolve(Path::new("/src")).unwrap().remove(0);
+        assert_eq!(
+            macros.expand("${sourceDir}/build/${presetName}"),
+            "/src/build/dev"
+        );
+        assert_eq!(macros.environment["BAR"], "foo-");
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(macros.expand("$env{PATH}"), path);
+        assert_eq!(macros.expand("$penv{PATH}"), path);
+        assert_eq!(macros.expand("$x ${unknown} $$"), "$x  $$");
+    }
+
+    #[test]
+    fn hides_presets_whose_condition_fails() {
+        let section = section(json!([
+            {"name": "never", "condition": {"type": "const", "value": false}},
+            {"name": "here", "condition": {
+                "type": "equals", "lhs": "${presetName}", "rhs": "here"}},
+            {"name": "elsewhere", "condition": {
+                "type": "notEquals", "lhs": "${presetName}", "rhs": "elsewhere"}},
+        ]));
+        assert_eq!(names(&section), ["here"]);
+    }
+}
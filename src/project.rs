@@ -1,25 +1,184 @@
-use crate::list_box::ListItemProvider;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::{file_api, list_box::ListItemProvider, parser};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetKind {
+    #[default]
+    Executable,
+    StaticLibrary,
+    SharedLibrary,
+    ModuleLibrary,
+    ObjectLibrary,
+    InterfaceLibrary,
+    Custom,
+}
+
+impl TargetKind {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            TargetKind::Executable => "executable",
+            TargetKind::StaticLibrary => "static",
+            TargetKind::SharedLibrary => "shared",
+            TargetKind::ModuleLibrary => "module",
+            TargetKind::ObjectLibrary => "object",
+            TargetKind::InterfaceLibrary => "interface",
+            TargetKind::Custom => "custom",
+        }
+    }
+
+    /// Maps the `type` of a CMake File API target object.
+    pub fn from_file_api(kind: &str) -> Option<Self> {
+        match kind {
+            "EXECUTABLE" => Some(TargetKind::Executable),
+            "STATIC_LIBRARY" => Some(TargetKind::StaticLibrary),
+            "SHARED_LIBRARY" => Some(TargetKind::SharedLibrary),
+            "MODULE_LIBRARY" => Some(TargetKind::ModuleLibrary),
+            "OBJECT_LIBRARY" => Some(TargetKind::ObjectLibrary),
+            "INTERFACE_LIBRARY" => Some(TargetKind::InterfaceLibrary),
+            "UTILITY" => Some(TargetKind::Custom),
+            _ => None,
+        }
+    }
+
+    /// Maps the library type keyword of an `add_library` call.
+    pub fn from_library_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "STATIC" => Some(TargetKind::StaticLibrary),
+            "SHARED" => Some(TargetKind::SharedLibrary),
+            "MODULE" => Some(TargetKind::ModuleLibrary),
+            "OBJECT" => Some(TargetKind::ObjectLibrary),
+            "INTERFACE" => Some(TargetKind::InterfaceLibrary),
+            _ => None,
+        }
+    }
+}
+
+/// How to launch an executable target.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct RunConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Relative to the source directory; the source directory when unset.
+    pub cwd: Option<PathBuf>,
+}
+
+impl RunConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The directory to run in, given the source directory.
+    pub fn working_dir(&self, source_dir: &Path) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) => source_dir.join(cwd),
+            None => source_dir.to_path_buf(),
+        }
+    }
+}
 
 #[derive(Default, Clone, Debug)]
 pub struct Project {
     pub target: String,
-    pub file_name: String,
+    pub kind: TargetKind,
+    /// The `OUTPUT_NAME` property, defaulting to the target name.
+    pub output_name: Option<String>,
+    /// The absolute `RUNTIME_OUTPUT_DIRECTORY`, defaulting to the binary
+    /// directory matching [`Project::dir`].
+    pub output_dir: Option<PathBuf>,
+    /// The file that defines the target, relative to the source directory.
+    pub source: PathBuf,
+    /// The directory whose `CMakeLists.txt` defines the target, relative to
+    /// the source directory.
+    pub dir: PathBuf,
+    pub line: usize,
+    /// The absolute path of the built binary, when known from the CMake File API.
+    pub artifact: Option<PathBuf>,
+    pub run: RunConfig,
+}
+
+/// Subfolders used by multi-config generators such as Ninja Multi-Config,
+/// Visual Studio and Xcode.
+const CONFIGURATIONS: [&str; 4] = ["Debug", "Release", "RelWithDebInfo", "MinSizeRel"];
+
+impl Project {
+    /// Finds the binary this target builds inside `build_dir`.
+    pub fn resolve_artifact(&self, build_dir: &Path) -> Result<PathBuf> {
+        if let Some(artifact) = self.artifact.as_ref().filter(|a| a.is_file()) {
+            return Ok(artifact.clone());
+        }
+
+        let dir = match &self.output_dir {
+            Some(dir) => dir.clone(),
+            None => build_dir.join(&self.dir),
+        };
+        let name = format!(
+            "{}{}",
+            self.output_name.as_deref().unwrap_or(&self.target),
+            std::env::consts::EXE_SUFFIX
+        );
+        let candidates: Vec<PathBuf> = std::iter::once(dir.join(&name))
+            .chain(CONFIGURATIONS.iter().map(|c| dir.join(c).join(&name)))
+            .collect();
+
+        candidates
+            .iter()
+            .find(|p| p.is_file())
+            .cloned()
+            .ok_or_else(|| {
+                let looked_in: Vec<_> = candidates
+                    .iter()
+                    .map(|p| format!("  {}", p.display()))
+                    .collect();
+                eyre!(
+                    "Could not find the binary of `{}`, looked in:\n{}",
+                    self.target,
+                    looked_in.join("\n")
+                )
+            })
+    }
 }
 
 impl ListItemProvider for Project {
     fn as_str(&self) -> &str {
         &self.target
     }
+
+    fn detail(&self) -> Option<String> {
+        Some(format!(
+            "{} {}:{}",
+            self.kind.to_str(),
+            self.source.display(),
+            self.line
+        ))
+    }
 }
 
-impl From<&str> for Project {
-    fn from(s: &str) -> Self {
-        let mut split = s.split(";;;");
-        let key = split.next().unwrap().to_string();
-        let value = split.next().unwrap().to_string();
-        Project {
-            target: key,
-            file_name: value,
+/// The targets of the project at `path`, from the CMake File API replies in
+/// `build_dir` for `configuration` or else parsed from the `CMakeLists.txt`
+/// files, with their saved run settings.
+pub fn discover(
+    path: &Path,
+    build_dir: &Path,
+    configuration: &str,
+    targets: &BTreeMap<String, RunConfig>,
+) -> Result<Vec<Project>> {
+    let mut projects = match file_api::refresh_list(build_dir, configuration)? {
+        Some(projects) => projects,
+        None => parser::refresh_list(path, build_dir)?,
+    };
+    for project in &mut projects {
+        if let Some(run) = targets.get(&project.target) {
+            project.run = run.clone();
         }
     }
+    Ok(projects)
 }
@@ -0,0 +1,331 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use eyre::{Result, eyre};
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use tokio::sync::{mpsc, watch};
+
+use crate::{ansi::Decoder, job::Output, utils};
+
+/// The terminal emulator state a program in a pseudo-terminal draws on.
+pub struct Screen {
+    pub parser: vt100::Parser,
+    /// Whether a program is running, i.e. the screen should be shown instead
+    /// of the output.
+    pub active: bool,
+}
+
+/// The app's end of a terminal session: the screen to render, and where to
+/// send key presses and size changes.
+pub struct Terminal {
+    screen: Arc<Mutex<Screen>>,
+    input: mpsc::Sender<Vec<u8>>,
+    size: watch::Sender<(u16, u16)>,
+}
+
+/// The program's end of a terminal session, see [`channel`].
+pub struct TerminalIo {
+    screen: Arc<Mutex<Screen>>,
+    input: mpsc::Receiver<Vec<u8>>,
+    size: watch::Receiver<(u16, u16)>,
+    /// How many lines scrolled off the top of the screen are kept.
+    scrollback: usize,
+}
+
+/// Creates a terminal session of `rows` by `cols` cells, keeping
+/// `scrollback` lines that scrolled off the top.
+pub fn channel(rows: u16, cols: u16, scrollback: usize) -> (Terminal, TerminalIo) {
+    let screen = Arc::new(Mutex::new(Screen {
+        parser: vt100::Parser::new(rows, cols, scrollback),
+        active: false,
+    }));
+    let (input_tx, input_rx) = mpsc::channel(50);
+    let (size_tx, size_rx) = watch::channel((rows, cols));
+    (
+        Terminal {
+            screen: screen.clone(),
+            input: input_tx,
+            size: size_tx,
+        },
+        TerminalIo {
+            screen,
+            input: input_rx,
+            size: size_rx,
+            scrollback,
+        },
+    )
+}
+
+impl Terminal {
+    pub fn screen(&self) -> MutexGuard<'_, Screen> {
+        lock(&self.screen)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.screen().active
+    }
+
+    /// Forwards a key press to the program as the bytes a terminal sends.
+    pub fn send_key(&self, key_event: KeyEvent) {
+        let application_cursor = self.screen().parser.screen().application_cursor();
+        let bytes = key_bytes(key_event, application_cursor);
+        if !bytes.is_empty() {
+            self.input.try_send(bytes).ok();
+        }
+    }
+
+    pub fn resize(&self, rows: u16, cols: u16) {
+        self.size.send_if_modified(|size| {
+            let changed = *size != (rows, cols);
+            *size = (rows, cols);
+            changed
+        });
+    }
+}
+
+fn lock(screen: &Mutex<Screen>) -> MutexGuard<'_, Screen> {
+    screen.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Runs `command` in a pseudo-terminal drawing on the session's screen. The
+/// lines it prints also go to the output as they complete.
+pub async fn spawn(
+    out: &Output,
+    io: TerminalIo,
+    command: &str,
+    args: &[&str],
+    cwd: &Path,
+    env: &BTreeMap<String, String>,
+    action: &str,
+) -> Result<()> {
+    if out.control.is_cancelled() {
+        return Err(eyre!("{action} cancelled"));
+    }
+    let TerminalIo {
+        screen,
+        mut input,
+        mut size,
+        scrollback,
+    } = io;
+    let (rows, cols) = *size.borrow_and_update();
+    let pair = native_pty_system()
+        .openpty(pty_size(rows, cols))
+        .map_err(|e| eyre!("failed to open a pseudo-terminal: {e:#}"))?;
+
+    let mut cmd = CommandBuilder::new(command);
+    cmd.args(args);
+    cmd.cwd(cwd);
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| eyre!("failed to run {command}: {e:#}"))?;
+    drop(pair.slave);
+    // The program leads a new session, so signals reach its process group.
+    out.control.set_child(child.process_id());
+
+    let master = pair.master;
+    let mut reader = master.try_clone_reader().map_err(|e| eyre!("{e:#}"))?;
+    let mut writer = master.take_writer().map_err(|e| eyre!("{e:#}"))?;
+    {
+        let mut screen = lock(&screen);
+        screen.parser = vt100::Parser::new(rows, cols, scrollback);
+        screen.active = true;
+    }
+
+    let reader_screen = screen.clone();
+    let reader_out = out.clone();
+    let runtime = tokio::runtime::Handle::current();
+    let reading = tokio::task::spawn_blocking(move || {
+        let mut decoder = Decoder::default();
+        let mut buffer = [0; 4096];
+        while let Ok(n @ 1..) = reader.read(&mut buffer) {
+            lock(&reader_screen).parser.process(&buffer[..n]);
+            let lines = decoder.feed(&buffer[..n]);
+            if !lines.is_empty() {
+                runtime
+                    .block_on(reader_out.send(Ok(Text::from(lines))))
+                    .ok();
+            }
+        }
+        decoder.finish()
+    });
+    let mut waiting = tokio::task::spawn_blocking(move || child.wait());
+
+    let status = loop {
+        tokio::select! {
+            status = &mut waiting => break status?,
+            Some(bytes) = input.recv() => {
+                if writer.write_all(&bytes).and_then(|_| writer.flush()).is_err() {
+                    continue;
+                }
+            }
+            Ok(()) = size.changed() => {
+                let (rows, cols) = *size.borrow_and_update();
+                master.resize(pty_size(rows, cols)).ok();
+                lock(&screen).parser.set_size(rows, cols);
+            }
+        }
+    };
+    out.control.set_child(None);
+    drop(writer);
+    // Give the reader a moment to process the last output. It may never
+    // finish when the program left children behind that keep the terminal.
+    let last = tokio::time::timeout(Duration::from_millis(100), reading).await;
+    lock(&screen).active = false;
+    if let Ok(Ok(Some(line))) = last {
+        out.send(Ok(Text::from(line))).await?;
+    }
+    let success = status.as_ref().is_ok_and(|s| s.success());
+    out.control
+        .set_exit_code(status.as_ref().ok().map(|s| s.exit_code() as i32));
+    if let Err(e) = status {
+        out.send(Err(e.into())).await?;
+    }
+    utils::report(out, action, success).await
+}
+
+/// The bytes a terminal sends for a key press.
+pub fn key_bytes(key_event: KeyEvent, application_cursor: bool) -> Vec<u8> {
+    let modifiers = key_event.modifiers;
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    // xterm encodes modifiers of cursor and function keys as a parameter.
+    let parameter = 1
+        + u8::from(modifiers.contains(KeyModifiers::SHIFT))
+        + 2 * u8::from(modifiers.contains(KeyModifiers::ALT))
+        + 4 * u8::from(ctrl);
+    let cursor = |c: char| match (parameter, application_cursor) {
+        (1, true) => format!("\x1bO{c}"),
+        (1, false) => format!("\x1b[{c}"),
+        _ => format!("\x1b[1;{parameter}{c}"),
+    };
+    let tilde = |n: u8| match parameter {
+        1 => format!("\x1b[{n}~"),
+        _ => format!("\x1b[{n};{parameter}~"),
+    };
+    let mut alt_prefix = modifiers.contains(KeyModifiers::ALT);
+    let bytes = match key_event.code {
+        KeyCode::Char(c) if ctrl => match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => vec![c as u8 - b'a' + 1],
+            '@' | ' ' | '2' => vec![0],
+            '[' | '3' => vec![0x1b],
+            '\\' | '4' => vec![0x1c],
+            ']' | '5' => vec![0x1d],
+            '^' | '6' => vec![0x1e],
+            '_' | '/' | '7' => vec![0x1f],
+            c => c.to_string().into_bytes(),
+        },
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Esc => vec![0x1b],
+        code => {
+            alt_prefix = false;
+            match code {
+                KeyCode::Up => cursor('A'),
+                KeyCode::Down => cursor('B'),
+                KeyCode::Right => cursor('C'),
+                KeyCode::Left => cursor('D'),
+                KeyCode::Home => cursor('H'),
+                KeyCode::End => cursor('F'),
+                KeyCode::Insert => tilde(2),
+                KeyCode::Delete => tilde(3),
+                KeyCode::PageUp => tilde(5),
+                KeyCode::PageDown => tilde(6),
+                KeyCode::F(n @ 1..=4) if parameter == 1 => {
+                    format!("\x1bO{}", (b'P' + n - 1) as char)
+                }
+                KeyCode::F(n @ 1..=4) => format!("\x1b[1;{parameter}{}", (b'P' + n - 1) as char),
+                KeyCode::F(n @ 5..=12) => tilde([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5]),
+                _ => String::new(),
+            }
+            .into_bytes()
+        }
+    };
+    if alt_prefix && !bytes.is_empty() {
+        [vec![0x1b], bytes].concat()
+    } else {
+        bytes
+    }
+}
+
+/// The visible rows of `screen`.
+pub fn lines(screen: &vt100::Screen) -> Vec<Line<'static>> {
+    let (rows, _) = screen.size();
+    (0..rows).map(|row| self::row(screen, row)).collect()
+}
+
+/// A row of cells as styled spans, without trailing blanks.
+fn row(screen: &vt100::Screen, row: u16) -> Line<'static> {
+    let (_, cols) = screen.size();
+    let cells: Vec<_> = (0..cols).filter_map(|col| screen.cell(row, col)).collect();
+    let end = cells
+        .iter()
+        .rposition(|c| c.has_contents() || style(c).bg.is_some_and(|bg| bg != Color::Reset))
+        .map_or(0, |i| i + 1);
+
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for cell in &cells[..end] {
+        if cell.is_wide_continuation() {
+            continue;
+        }
+        let contents = match cell.contents() {
+            c if c.is_empty() => " ".to_string(),
+            c => c,
+        };
+        let style = style(cell);
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push_str(&contents),
+            _ => spans.push(Span::styled(contents, style)),
+        }
+    }
+    Line::from(spans)
+}
+
+fn style(cell: &vt100::Cell) -> Style {
+    let mut style = Style::new()
+        .fg(color(cell.fgcolor()))
+        .bg(color(cell.bgcolor()));
+    for (set, modifier) in [
+        (cell.bold(), Modifier::BOLD),
+        (cell.italic(), Modifier::ITALIC),
+        (cell.underline(), Modifier::UNDERLINED),
+        (cell.inverse(), Modifier::REVERSED),
+    ] {
+        if set {
+            style = style.add_modifier(modifier);
+        }
+    }
+    style
+}
+
+fn color(color: vt100::Color) -> Color {
+    match color {
+        vt100::Color::Default => Color::Reset,
+        vt100::Color::Idx(i) => Color::Indexed(i),
+        vt100::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    }
+}
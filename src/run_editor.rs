@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use super::*;
+use crate::{
+    config::{
+        Config,
+        keys::{KeyAction, Keymap},
+        theme::Theme,
+    },
+    project::RunConfig,
+    text_input::{InputEvent, TextInput},
+    utils,
+};
+
+/// A popup editing the arguments, environment and working directory used to
+/// run a target.
+pub struct RunEditor {
+    title: String,
+    fields: [TextInput; 3],
+    focused: usize,
+    theme: Theme,
+}
+
+pub enum EditorEvent {
+    Save(RunConfig),
+    Cancel,
+    Editing,
+}
+
+impl RunEditor {
+    pub fn new(target: &str, run: &RunConfig, config: &Config) -> Self {
+        let env: Vec<String> = run.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        let cwd = run
+            .cwd
+            .as_ref()
+            .map(|c| c.display().to_string())
+            .unwrap_or_default();
+        let mut fields = [
+            TextInput::new("Arguments", utils::join_args(&run.args)),
+            TextInput::new("Environment (NAME=value ...)", utils::join_args(&env)),
+            TextInput::new("Working directory", cwd),
+        ]
+        .map(|field| field.theme(config.theme));
+        for field in fields.iter_mut().skip(1) {
+            field.active = false;
+        }
+        Self {
+            title: format!(
+                " Run {target} (tab next field, {}) ",
+                config
+                    .keys
+                    .hints(&[(KeyAction::Select, "save"), (KeyAction::Cancel, "cancel")])
+            ),
+            fields,
+            focused: 0,
+            theme: config.theme,
+        }
+    }
+
+    pub fn handle_key_event(&mut self, key_event: KeyEvent, keys: &Keymap) -> EditorEvent {
+        let next = (self.focused + 1) % self.fields.len();
+        let prev = (self.focused + self.fields.len() - 1) % self.fields.len();
+        match key_event.code {
+            KeyCode::Tab => self.focus(next),
+            KeyCode::BackTab => self.focus(prev),
+            _ if keys.is(KeyAction::Down, &key_event) => self.focus(next),
+            _ if keys.is(KeyAction::Up, &key_event) => self.focus(prev),
+            _ if keys.is(KeyAction::Select, &key_event) => return EditorEvent::Save(self.config()),
+            _ if keys.is(KeyAction::Cancel, &key_event) => return EditorEvent::Cancel,
+            _ => match self.fields[self.focused].handle_key_event(key_event) {
+                InputEvent::Submit(_) => return EditorEvent::Save(self.config()),
+                InputEvent::Cancel => return EditorEvent::Cancel,
+                InputEvent::Editing => {}
+            },
+        }
+        EditorEvent::Editing
+    }
+
+    fn focus(&mut self, index: usize) {
+        self.fields[self.focused].active = false;
+        self.focused = index;
+        self.fields[self.focused].active = true;
+    }
+
+    fn config(&self) -> RunConfig {
+        let [args, env, cwd] = &self.fields;
+        RunConfig {
+            args: utils::split_args(&args.value),
+            env: utils::split_args(&env.value)
+                .iter()
+                .filter_map(|e| e.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            cwd: Some(cwd.value.trim())
+                .filter(|c| !c.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+}
+
+impl Widget for &RunEditor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .border_type(BorderType::Double)
+            .border_style(Style::new().fg(self.theme.active_border))
+            .title(self.title.as_str());
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let field_area = Rect {
+                y: inner.y + i as u16 * 3,
+                height: 3,
+                ..inner
+            };
+            if field_area.bottom() <= inner.bottom() {
+                field.render(field_area, buf);
+            }
+        }
+    }
+}
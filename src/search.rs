@@ -0,0 +1,231 @@
+use std::cell::{Ref, RefCell};
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::theme::Theme,
+    line_buffer::{LineBuffer, Scroll},
+    text_input::TextInput,
+};
+
+/// The style of the matches.
+pub const MATCH: Style = Style::new().fg(Color::Black).bg(Color::Yellow);
+/// The style of the matches in the current line.
+pub const CURRENT: Style = Style::new().fg(Color::Black).bg(Color::LightCyan);
+
+/// A regex search in the output pane, optionally hiding the lines that do not
+/// match.
+pub struct Search {
+    pub input: TextInput,
+    /// Whether the pattern is being typed.
+    pub editing: bool,
+    /// Whether only the matching lines are shown.
+    pub filter: bool,
+    regex: Option<Regex>,
+    /// Whether the pattern fails to compile.
+    pub invalid: bool,
+    /// The line number of the current match.
+    pub current: Option<usize>,
+    /// The line number at the top of the view when the search started, the
+    /// incremental search starts there.
+    pub origin: usize,
+    /// The position among the matching lines when filtering.
+    pub scroll: Scroll,
+    /// The matches in the last buffer searched, so that each frame does not
+    /// search the whole scrollback again.
+    matches: RefCell<Matches>,
+}
+
+/// The matching lines of a buffer, searched up to a revision of it.
+#[derive(Debug, Default)]
+struct Matches {
+    /// The [`LineBuffer::revision`] searched.
+    revision: Option<(usize, usize)>,
+    /// The number of the first complete line not searched yet.
+    end: usize,
+    lines: Vec<usize>,
+}
+
+impl Search {
+    pub fn new(filter: bool, pattern: &str, origin: usize, theme: Theme) -> Self {
+        let title = if filter {
+            "Filter (regex)"
+        } else {
+            "Search (regex)"
+        };
+        let mut search = Self {
+            input: TextInput::new(title, pattern).theme(theme),
+            editing: true,
+            filter,
+            regex: None,
+            invalid: false,
+            current: None,
+            origin,
+            scroll: Scroll::default(),
+            matches: Default::default(),
+        };
+        search.update();
+        search
+    }
+
+    /// Compiles the typed pattern, ignoring case unless it has an uppercase
+    /// letter.
+    pub fn update(&mut self) {
+        let pattern = &self.input.value;
+        *self.matches.get_mut() = Matches::default();
+        self.regex = None;
+        self.invalid = false;
+        if pattern.is_empty() {
+            return;
+        }
+        match RegexBuilder::new(pattern)
+            .case_insensitive(!pattern.chars().any(char::is_uppercase))
+            .build()
+        {
+            Ok(regex) => self.regex = Some(regex),
+            Err(_) => self.invalid = true,
+        }
+    }
+
+    pub fn regex(&self) -> Option<&Regex> {
+        self.regex.as_ref()
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.input.value
+    }
+
+    /// The numbers of the matching lines, all of them without a pattern.
+    /// Only the lines pushed since the last call are searched.
+    pub fn matches(&self, output: &LineBuffer) -> Ref<'_, [usize]> {
+        let revision = output.revision();
+        if self.matches.borrow().revision != Some(revision) {
+            let mut matches = self.matches.borrow_mut();
+            if matches.revision.map(|(id, _)| id) != Some(revision.0) {
+                *matches = Matches::default();
+            }
+            // The oldest lines may have been dropped, and the partial line
+            // is searched again as it changes.
+            let (first, end) = (output.first(), matches.end);
+            matches.lines.retain(|&n| first <= n && n < end);
+            let searched = end.saturating_sub(first);
+            let new = output
+                .numbered()
+                .skip(searched)
+                .filter(|(_, line)| self.is_match(line))
+                .map(|(number, _)| number);
+            matches.lines.extend(new);
+            matches.end = output.end();
+            matches.revision = Some(revision);
+        }
+        Ref::map(self.matches.borrow(), |m| m.lines.as_slice())
+    }
+
+    fn is_match(&self, line: &Line) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(&line.to_string()),
+            None => !self.invalid,
+        }
+    }
+
+    /// The first matching line from `from` on, or before it when going
+    /// backward, wrapping around.
+    pub fn find(&self, output: &LineBuffer, from: usize, forward: bool) -> Option<usize> {
+        self.regex.as_ref()?;
+        let matches = self.matches(output);
+        if forward {
+            matches
+                .iter()
+                .find(|&&n| n >= from)
+                .or(matches.first())
+                .copied()
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&n| n <= from)
+                .or(matches.last())
+                .copied()
+        }
+    }
+}
+
+/// Styles the parts of `line` matching `regex` with `style`.
+pub fn highlight(line: Line<'static>, regex: &Regex, style: Style) -> Line<'static> {
+    let text = line.to_string();
+    let ranges: Vec<_> = regex
+        .find_iter(&text)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.range())
+        .collect();
+    if ranges.is_empty() {
+        return line;
+    }
+    let mut spans = vec![];
+    let mut start = 0;
+    for span in &line.spans {
+        let end = start + span.content.len();
+        // Split the span at the boundaries of the matches inside it.
+        let mut cuts = vec![start, end];
+        for range in &ranges {
+            cuts.extend(
+                [range.start, range.end]
+                    .into_iter()
+                    .filter(|&c| start < c && c < end),
+            );
+        }
+        cuts.sort_unstable();
+        for part in cuts.windows(2).filter(|part| part[0] < part[1]) {
+            let matched = ranges
+                .iter()
+                .any(|r| r.start <= part[0] && part[1] <= r.end);
+            let content = text[part[0]..part[1]].to_string();
+            spans.push(match matched {
+                true => Span::styled(content, span.style.patch(style)),
+                false => Span::styled(content, span.style),
+            });
+        }
+        start = end;
+    }
+    Line { spans, ..line }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(pattern: &str) -> Search {
+        Search::new(false, pattern, 0, Theme::default())
+    }
+
+    #[test]
+    fn follows_pushed_and_dropped_lines() {
+        let search = search("warn");
+        let mut output = LineBuffer::new(3);
+        output.push(Line::raw("warning: a"));
+        output.push(Line::raw("ok"));
+        assert_eq!(*search.matches(&output), [0]);
+
+        output.set_partial(Some(Line::raw("warn")));
+        assert_eq!(*search.matches(&output), [0, 2]);
+        output.push(Line::raw("done"));
+        output.push(Line::raw("warning: b"));
+        // Line 0 is dropped past the limit.
+        assert_eq!(*search.matches(&output), [3]);
+    }
+
+    #[test]
+    fn searches_each_buffer_separately() {
+        let search = search("x");
+        let (mut a, mut b) = (LineBuffer::default(), LineBuffer::default());
+        a.push(Line::raw("x"));
+        b.push(Line::raw("y"));
+        b.push(Line::raw("x"));
+        assert_eq!(*search.matches(&a), [0]);
+        assert_eq!(*search.matches(&b), [1]);
+    }
+}
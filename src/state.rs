@@ -0,0 +1,53 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::project::RunConfig;
+
+const STATE_FILE: &str = ".cmake-runner/state.toml";
+
+/// Settings remembered across sessions, stored in the source directory.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct State {
+    /// The selected project, by target name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// The selected action, by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, RunConfig>,
+}
+
+impl State {
+    pub fn path(source_dir: &Path) -> PathBuf {
+        source_dir.join(STATE_FILE)
+    }
+
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let path = Self::path(source_dir);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let file = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&file).wrap_err_with(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, source_dir: &Path) -> Result<()> {
+        let path = Self::path(source_dir);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+}
@@ -0,0 +1,71 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::*;
+use crate::config::theme::Theme;
+
+/// A single line prompt shown in a popup, e.g. for a regex.
+#[derive(Default, Clone, Debug)]
+pub struct TextInput {
+    title: String,
+    pub value: String,
+    pub active: bool,
+    theme: Theme,
+}
+
+pub enum InputEvent {
+    Submit(String),
+    Cancel,
+    Editing,
+}
+
+impl TextInput {
+    pub fn new(title: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            title: format!(" {} ", title.into()),
+            value: value.into(),
+            active: true,
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> InputEvent {
+        match key_event.code {
+            KeyCode::Enter => return InputEvent::Submit(self.value.clone()),
+            KeyCode::Esc => return InputEvent::Cancel,
+            KeyCode::Backspace => {
+                self.value.pop();
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.value.clear();
+            }
+            KeyCode::Char(c) => self.value.push(c),
+            _ => {}
+        }
+        InputEvent::Editing
+    }
+}
+
+impl Widget for &TextInput {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let mut line = Line::from(vec![Span::raw(" "), Span::raw(self.value.as_str())]);
+        let block = if self.active {
+            line.push_span(Span::raw(" ").reversed());
+            Block::bordered()
+                .border_type(BorderType::Double)
+                .border_style(Style::new().fg(self.theme.active_border))
+        } else {
+            Block::bordered()
+                .border_type(BorderType::Plain)
+                .border_style(Style::new().fg(self.theme.border))
+        };
+        Paragraph::new(line)
+            .block(block.title(self.title.as_str()))
+            .render(area, buf);
+    }
+}
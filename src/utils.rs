@@ -1,92 +1,223 @@
-use std::process::Stdio;
+use std::{
+    collections::BTreeMap,
+    env::consts::EXE_EXTENSION,
+    io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use ansi_to_tui::IntoText;
 use eyre::Result;
-use ratatui::{style::Stylize, text::Text};
+use ratatui::{
+    style::{Style, Stylize},
+    text::Text,
+};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, BufReader},
+    io::{AsyncRead, AsyncReadExt},
     process::Command,
-    sync::mpsc,
 };
 
+use crate::{ansi::Decoder, job::Output};
+
+/// Looks up `program` in the `PATH`.
+pub fn which(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| {
+            let mut candidate = dir.join(program).into_os_string();
+            // Appended, as `with_extension` would replace the `.1` of `lldb-18.1`.
+            if !EXE_EXTENSION.is_empty() {
+                candidate.push(".");
+                candidate.push(EXE_EXTENSION);
+            }
+            PathBuf::from(candidate)
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Splits a command line into arguments, honouring single and double quotes
+/// and backslash escapes.
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_default();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => current.get_or_insert_default().push(c),
+            (_, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.get_or_insert_default().push(escaped);
+                }
+            }
+            (_, c) => current.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(current);
+    args
+}
+
+/// The inverse of [`split_args`], quoting arguments where needed.
+pub fn join_args<S: AsRef<str>>(args: &[S]) -> String {
+    args.iter()
+        .map(|arg| {
+            let arg = arg.as_ref();
+            let plain = !arg.is_empty()
+                && !arg
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '\\'));
+            if plain {
+                arg.to_string()
+            } else {
+                format!("'{}'", arg.replace('\'', r#"'"'"'"#))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub async fn spawn_command(
-    out: &mpsc::Sender<Result<Text<'static>>>,
-    mut in_rx: Option<mpsc::Receiver<Result<String>>>,
+    out: &Output,
     command: &str,
     args: &[&str],
     path: &str,
     action: &str,
 ) -> Result<()> {
-    let mut child = Command::new(command)
+    spawn_with_env(
+        out,
+        command,
+        args,
+        Path::new(path),
+        &BTreeMap::new(),
+        action,
+    )
+    .await
+}
+
+/// Like [`spawn_command`], with `env` added to the environment.
+pub async fn spawn_with_env(
+    out: &Output,
+    command: &str,
+    args: &[&str],
+    cwd: &Path,
+    env: &BTreeMap<String, String>,
+    action: &str,
+) -> Result<()> {
+    if out.control.is_cancelled() {
+        return Err(eyre::eyre!("{action} cancelled"));
+    }
+    let mut command = Command::new(command);
+    command
         .args(args)
-        .current_dir(path)
+        .current_dir(cwd)
+        .envs(env)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .stdin(Stdio::piped())
-        .spawn()?;
-
-    if let Some(mut in_rx) = in_rx.take() {
-        let mut stdin = child.stdin.take().expect("stdin not piped");
-        tokio::spawn(async move {
-            while let Some(Ok(input)) = in_rx.recv().await {
-                if stdin.write_all(input.as_bytes()).await.is_err() {
-                    break;
-                }
-            }
-        });
-    }
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    // Its own process group, so that stopping it reaches its children.
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command.spawn()?;
+    out.control.set_child(child.id());
 
     let stdout = child.stdout.take().expect("stdout not piped");
     let stderr = child.stderr.take().expect("stderr not piped");
 
-    let mut stdout_reader = BufReader::new(stdout);
-    let mut stderr_reader = BufReader::new(stderr);
-
-    let out_clone = out.clone();
-    tokio::spawn(async move {
-        let mut buffer = [0; 1024];
-        loop {
-            match stdout_reader.read(&mut buffer).await {
-                Ok(0) => break,
-                Ok(n) => {
-                    let text = (&buffer[..n]).into_text().unwrap();
-                    if out_clone.send(Ok(text)).await.is_err() {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    if out_clone.send(Err(e.into())).await.is_err() {
-                        break;
-                    }
-                }
+    let stdout = tokio::spawn(forward(stdout, out.clone(), Style::new()));
+    let stderr = tokio::spawn(forward(stderr, out.clone(), Style::new().fg(out.stderr)));
+
+    let status = child.wait().await;
+    // The rest of the output comes before the report.
+    let _ = tokio::join!(stdout, stderr);
+    finish(out, status, action).await
+}
+
+/// Like [`spawn_with_env`], but the program reads and writes the standard
+/// streams of this process instead of `out`, which only gets the report.
+pub async fn spawn_inherited(
+    out: &Output,
+    command: &str,
+    args: &[&str],
+    cwd: &Path,
+    env: &BTreeMap<String, String>,
+    action: &str,
+) -> Result<()> {
+    if out.control.is_cancelled() {
+        return Err(eyre::eyre!("{action} cancelled"));
+    }
+    // It stays in the foreground process group to be able to read the
+    // terminal, which also sends it Ctrl+C.
+    let mut child = Command::new(command)
+        .args(args)
+        .current_dir(cwd)
+        .envs(env)
+        .kill_on_drop(true)
+        .spawn()?;
+    out.control.set_child(child.id());
+    let status = child.wait().await;
+    finish(out, status, action).await
+}
+
+/// Records how the process exited and reports it.
+async fn finish(out: &Output, status: io::Result<ExitStatus>, action: &str) -> Result<()> {
+    out.control.set_child(None);
+    let status = status?;
+    out.control.set_exit_code(status.code());
+    report(out, action, status.success()).await
+}
+
+/// Sends what `reader` outputs line by line, styled with `style` where the
+/// program sets no colors itself.
+async fn forward(mut reader: impl AsyncRead + Unpin, out: Output, style: Style) {
+    let mut decoder = Decoder::default();
+    let mut buffer = [0; 4096];
+    loop {
+        let n = match reader.read(&mut buffer).await {
+            Ok(n) => n,
+            Err(e) => {
+                let _ = out.send(Err(e.into())).await;
+                return;
             }
-        }
-    });
-
-    let out_clone = out.clone();
-    tokio::spawn(async move {
-        let mut buffer = [0; 1024];
-        loop {
-            match stderr_reader.read(&mut buffer).await {
-                Ok(0) => break,
-                Ok(n) => {
-                    let text = (&buffer[..n]).into_text().unwrap().light_magenta();
-                    if out_clone.send(Ok(text)).await.is_err() {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    if out_clone.send(Err(e.into())).await.is_err() {
-                        break;
-                    }
-                }
+        };
+        if n == 0 {
+            if let Some(line) = decoder.finish() {
+                let _ = out.send(Ok(Text::from(line).patch_style(style))).await;
             }
+            return;
         }
-    });
+        let lines = decoder.feed(&buffer[..n]);
+        if !lines.is_empty()
+            && out
+                .send(Ok(Text::from(lines).patch_style(style)))
+                .await
+                .is_err()
+        {
+            return;
+        }
+        if let Some(partial) = decoder.partial()
+            && out.send_partial(partial.patch_style(style)).await.is_err()
+        {
+            return;
+        }
+    }
+}
 
-    let status = child.wait().await?;
+/// Prints whether `action` finished or failed, failing in the latter case so
+/// that the following steps are skipped.
+pub async fn report(out: &Output, action: &str, success: bool) -> Result<()> {
     out.send(Ok(Text::raw("\n"))).await?;
-    if status.success() {
+    if success {
         out.send(Ok(Text::raw(format!("=== {action} finished")).light_green()))
             .await?;
         out.send(Ok(Text::raw("\n"))).await?;
@@ -99,3 +230,63 @@ pub async fn spawn_command(
         Err(eyre::eyre!(""))
     }
 }
+
+/// Formats `time` as local `HH:MM:SS`.
+pub fn clock(time: SystemTime) -> String {
+    let [_, _, _, hour, minute, second] = local_time(time);
+    format!("{hour:02}:{minute:02}:{second:02}")
+}
+
+/// Formats `time` as local `YYYYMMDD-HHMMSS`, for file names.
+pub fn timestamp(time: SystemTime) -> String {
+    let [year, month, day, hour, minute, second] = local_time(time);
+    format!("{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}")
+}
+
+/// The local year, month, day, hour, minute and second of `time`, UTC when
+/// the time zone is unknown.
+fn local_time(time: SystemTime) -> [i64; 6] {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    #[cfg(unix)]
+    {
+        let time = secs as libc::time_t;
+        // SAFETY: `tm` is plain data and both pointers are valid.
+        unsafe {
+            let mut tm: libc::tm = std::mem::zeroed();
+            if !libc::localtime_r(&time, &mut tm).is_null() {
+                return [
+                    i64::from(tm.tm_year) + 1900,
+                    i64::from(tm.tm_mon) + 1,
+                    i64::from(tm.tm_mday),
+                    i64::from(tm.tm_hour),
+                    i64::from(tm.tm_min),
+                    i64::from(tm.tm_sec),
+                ];
+            }
+        }
+    }
+    // The civil date of the day, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = secs.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let secs = secs.rem_euclid(86_400);
+    [year, month, day, secs / 3600, secs / 60 % 60, secs % 60]
+}
+
+/// Formats `duration` like `850ms`, `3.2s` or `2m 05s`.
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    match millis {
+        0..1_000 => format!("{millis}ms"),
+        1_000..60_000 => format!("{:.1}s", duration.as_secs_f32()),
+        _ => format!("{}m {:02}s", millis / 60_000, millis / 1_000 % 60),
+    }
+}
@@ -0,0 +1,4 @@
+add_library(helpers STATIC ${CMAKE_CURRENT_LIST_DIR}/../helpers.c)
+
+# Included twice: the second time is ignored.
+include(${CMAKE_CURRENT_LIST_DIR}/Targets.cmake)